@@ -5,6 +5,27 @@ defmodule ExditorJS do
 
   This module provides functions to convert HTML and Markdown content into
   EditorJS block format, which can be used with the Editor.js library.
+
+  ## Embed services
+
+  Links and iframes of known services (YouTube, Vimeo, ...) become embed
+  blocks. Additional services can be configured in the application env,
+  either inline or as the path to a JSON or TOML config file:
+
+      config :exditorjs, :embeds,
+        services: [
+          %{
+            name: "trailmap",
+            pattern: ~S"maps\\.example\\.com/t/(\\w+)",
+            embed_url: "https://maps.example.com/embed/{}",
+            width: 800,
+            height: 600
+          }
+        ]
+
+      config :exditorjs, :embeds, "priv/embeds.toml"
+
+  Set `defaults: false` to drop the built-in services.
   """
 
   version = Mix.Project.config()[:version]
@@ -33,7 +54,7 @@ defmodule ExditorJS do
       {:ok, [%{"type" => "list", ...}]}
   """
   def html_to_editorjs(html) do
-    case html_to_editorjs_nif(html) do
+    case html_to_editorjs_nif(html, embed_config()) do
       {:ok, json} -> ExditorJS.JSON.decode(json, json_library())
       {:error, reason} -> {:error, reason}
     end
@@ -45,6 +66,9 @@ defmodule ExditorJS do
   Takes a string containing Markdown and returns a list of EditorJS blocks
   that can be used with Editor.js.
 
+  YAML (`---`) or TOML (`+++`) front matter at the start of the document is
+  not converted to blocks; it is returned in the document's `"meta"` map.
+
   ## Examples
 
       iex> ExditorJS.markdown_to_editorjs("# Heading\\n\\nParagraph text")
@@ -54,12 +78,49 @@ defmodule ExditorJS do
       {:ok, [%{"type" => "list", ...}]}
   """
   def markdown_to_editorjs(markdown) do
-    case markdown_to_editorjs_nif(markdown) do
+    case markdown_to_editorjs_nif(markdown, embed_config()) do
       {:ok, json} -> ExditorJS.JSON.decode(json, json_library())
       {:error, reason} -> {:error, reason}
     end
   end
 
+  # The encoded config is cached until the app env changes, and the native
+  # side builds each distinct config's registry only once
+  defp embed_config do
+    config = Application.get_env(:exditorjs, :embeds)
+
+    case :persistent_term.get({__MODULE__, :embeds}, nil) do
+      {^config, encoded} ->
+        encoded
+
+      _ ->
+        encoded = encode_embed_config(config)
+        :persistent_term.put({__MODULE__, :embeds}, {config, encoded})
+        encoded
+    end
+  end
+
+  defp encode_embed_config(nil), do: nil
+  defp encode_embed_config(path) when is_binary(path), do: {:file, path}
+
+  defp encode_embed_config(config) do
+    case ExditorJS.JSON.encode(json_value(config), json_library()) do
+      {:ok, json} -> {:json, json}
+      {:error, reason} -> raise ArgumentError, "invalid :embeds config: #{inspect(reason)}"
+    end
+  end
+
+  defp json_value(list) when is_list(list) do
+    if list != [] and Keyword.keyword?(list) do
+      Map.new(list, fn {key, value} -> {key, json_value(value)} end)
+    else
+      Enum.map(list, &json_value/1)
+    end
+  end
+
+  defp json_value(%{} = map), do: Map.new(map, fn {key, value} -> {key, json_value(value)} end)
+  defp json_value(value), do: value
+
   defp json_library do
     case Application.get_env(:exditorjs, :json_library) do
       nil ->
@@ -94,11 +155,11 @@ defmodule ExditorJS do
   end
 
   # Private NIF functions
-  defp html_to_editorjs_nif(_html) do
+  defp html_to_editorjs_nif(_html, _embeds) do
     :erlang.nif_error(:not_loaded)
   end
 
-  defp markdown_to_editorjs_nif(_markdown) do
+  defp markdown_to_editorjs_nif(_markdown, _embeds) do
     :erlang.nif_error(:not_loaded)
   end
 end
@@ -30,6 +30,7 @@ defmodule ExditorJS.MixProject do
         "lib",
         "mix.exs",
         "README*",
+        "CHANGELOG*",
         "LICENSE",
         "native/exditorjs_native/src",
         "native/exditorjs_native/.cargo",
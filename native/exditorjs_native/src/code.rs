@@ -0,0 +1,207 @@
+//! Code block language handling shared by the HTML and Markdown parsers
+
+use crate::models::{CodeData, DiagramData, EditorJsBlock};
+use crate::options::ConversionOptions;
+
+/// Common short names and alternative spellings of languages, mapped to the
+/// canonical language id
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("ts", "typescript"),
+    ("py", "python"),
+    ("py3", "python"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erl", "erlang"),
+    ("yml", "yaml"),
+    ("md", "markdown"),
+    ("shell", "bash"),
+    ("zsh", "bash"),
+    ("c++", "cpp"),
+    ("cc", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("cs", "csharp"),
+    ("c#", "csharp"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("golang", "go"),
+    ("ps1", "powershell"),
+    ("pwsh", "powershell"),
+    ("htm", "html"),
+    ("xhtml", "html"),
+    ("objc", "objectivec"),
+];
+
+/// Class names added by syntax highlighters that do not name a language
+const NON_LANGUAGE_CLASSES: &[&str] = &[
+    "hljs",
+    "highlight",
+    "highlighted",
+    "sourcecode",
+    "source-code",
+    "code",
+    "prettyprint",
+    "line-numbers",
+    "linenums",
+    "notranslate",
+];
+
+/// Normalizes a language id: lowercases it, strips `language-`/`lang-` prefixes
+/// and maps aliases such as `js` to their canonical id
+pub(crate) fn normalize_language(language: &str) -> Option<String> {
+    let language = language.trim().to_lowercase();
+    let language = language
+        .strip_prefix("language-")
+        .or_else(|| language.strip_prefix("lang-"))
+        .unwrap_or(&language);
+
+    if language.is_empty() {
+        return None;
+    }
+
+    let canonical = LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language, |(_, canonical)| canonical);
+    Some(canonical.to_string())
+}
+
+/// Builds a code block, or a diagram block when the language is configured in
+/// [`ConversionOptions::diagram_blocks`]
+pub(crate) fn code_block(
+    code: String,
+    language: Option<String>,
+    options: &ConversionOptions,
+) -> EditorJsBlock {
+    let block_type = language.as_deref().and_then(|language| {
+        options
+            .diagram_blocks
+            .iter()
+            .find(|(diagram_language, _)| {
+                normalize_language(diagram_language).as_deref() == Some(language)
+            })
+            .map(|(_, block_type)| block_type.clone())
+    });
+
+    match (block_type, language) {
+        (Some(block_type), Some(language)) => EditorJsBlock::Diagram {
+            block_type,
+            data: DiagramData { code, language },
+        },
+        (_, language) => EditorJsBlock::Code {
+            data: CodeData { code, language },
+        },
+    }
+}
+
+/// Language of a code element from its `class` attribute value. A
+/// `language-`/`lang-` prefixed class wins; otherwise the first class that is
+/// not a highlighter marker is used.
+pub(crate) fn language_from_classes(classes: &str) -> Option<String> {
+    let classes: Vec<&str> = classes.split_whitespace().collect();
+
+    classes
+        .iter()
+        .find(|class| class.starts_with("language-") || class.starts_with("lang-"))
+        .or_else(|| {
+            classes.iter().find(|class| {
+                !NON_LANGUAGE_CLASSES.contains(&class.to_lowercase().as_str())
+                    && !class.starts_with("hljs-")
+            })
+        })
+        .and_then(|class| normalize_language(class))
+}
+
+/// Language of a fenced code block from its info string. The first word names
+/// the language (`rust title="main.rs"`); Pandoc-style attribute blocks
+/// (`{.rust title="main.rs"}`) use their first class.
+pub(crate) fn language_from_info_string(info: &str) -> Option<String> {
+    let info = info.trim();
+
+    if let Some(attributes) = info.strip_prefix('{') {
+        let attributes = attributes.trim_end_matches('}');
+        return attributes
+            .split_whitespace()
+            .find_map(|attribute| attribute.strip_prefix('.'))
+            .and_then(normalize_language);
+    }
+
+    info.split(|c: char| c.is_whitespace() || c == '{' || c == ',')
+        .next()
+        .and_then(normalize_language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_language() {
+        assert_eq!(normalize_language("JS").as_deref(), Some("javascript"));
+        assert_eq!(normalize_language("language-rust").as_deref(), Some("rust"));
+        assert_eq!(normalize_language("lang-py").as_deref(), Some("python"));
+        assert_eq!(normalize_language("elixir").as_deref(), Some("elixir"));
+        assert_eq!(normalize_language("  "), None);
+    }
+
+    #[test]
+    fn test_language_from_classes() {
+        assert_eq!(
+            language_from_classes("hljs language-ts").as_deref(),
+            Some("typescript")
+        );
+        assert_eq!(
+            language_from_classes("sourceCode rb").as_deref(),
+            Some("ruby")
+        );
+        assert_eq!(language_from_classes("hljs"), None);
+    }
+
+    #[test]
+    fn test_language_from_info_string() {
+        assert_eq!(
+            language_from_info_string(r#"rust title="main.rs""#).as_deref(),
+            Some("rust")
+        );
+        assert_eq!(
+            language_from_info_string(r#"{.js .numberLines startFrom="10"}"#).as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(
+            language_from_info_string("python{linenos=true}").as_deref(),
+            Some("python")
+        );
+        assert_eq!(language_from_info_string(""), None);
+    }
+
+    #[test]
+    fn test_code_block_maps_diagram_languages() {
+        let options = ConversionOptions {
+            diagram_blocks: [("Mermaid".to_string(), "diagram".to_string())].into(),
+            ..Default::default()
+        };
+
+        let block = code_block(
+            "graph TD".to_string(),
+            Some("mermaid".to_string()),
+            &options,
+        );
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["type"], "diagram");
+        assert_eq!(json["data"]["code"], "graph TD");
+        assert_eq!(json["data"]["language"], "mermaid");
+        assert_eq!(block.with_ids()[0].block_type, "diagram");
+
+        let block = code_block(
+            "fn main() {}".to_string(),
+            Some("rust".to_string()),
+            &options,
+        );
+        assert!(matches!(block, EditorJsBlock::Code { .. }));
+    }
+}
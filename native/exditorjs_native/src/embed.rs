@@ -1,208 +1,947 @@
-/// Embed service detection and parsing utilities
-use regex::Regex;
+//! Embed service detection and parsing utilities
+
+use crate::error::{Error, Result};
+use crate::link_tool::standalone_link;
+use crate::models::{EditorJsBlock, EmbedData, EmbedMeta};
+use regex::{Captures, Regex};
+use std::path::Path;
+
+mod maps;
+mod media;
+pub mod oembed;
+mod social;
+mod video;
+
+pub use maps::ShortLinkResolver;
+pub(crate) use media::media_block;
+pub use media::media_type;
+pub(crate) use social::{parse_snippet, parse_snippet_html};
 
 /// Represents an embed service configuration
 #[derive(Debug, Clone)]
 pub struct EmbedService {
-    pub name: &'static str,
+    pub name: String,
+    /// Matches URLs of the service; its capture groups extract the ids used in
+    /// the embed URL
     pub regex: Regex,
-    pub embed_url_template: &'static str,
+    /// Embed URL with placeholders for the captured ids: `{}` takes the next
+    /// capture group, `{1}` a numbered group and `{name}` a named group
+    pub embed_url_template: String,
     pub width: u32,
     pub height: u32,
+    /// Hosts serving the service (a leading `www.` is ignored). URLs on other
+    /// hosts never match; an empty list accepts any host.
+    pub hosts: Vec<String>,
+    /// Matches embed (iframe `src`) URLs of the service
+    pub embed_regex: Option<Regex>,
+    /// Canonical page URL built from the captures of [`EmbedService::embed_regex`]
+    pub source_url_template: Option<String>,
+    /// Computes the URLs of a matched URL in place of the templates
+    pub resolver: Option<EmbedResolver>,
 }
 
-lazy_static::lazy_static! {
-    static ref EMBED_SERVICES: Vec<EmbedService> = vec![
-        EmbedService {
-            name: "youtube",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?(?:youtube\.com/watch\?v=|youtu\.be/)([a-zA-Z0-9_-]{11})").unwrap(),
-            embed_url_template: "https://www.youtube.com/embed/{}",
-            width: 580,
-            height: 320,
-        },
-        EmbedService {
-            name: "vimeo",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?vimeo\.com/(\d+)").unwrap(),
-            embed_url_template: "https://player.vimeo.com/video/{}",
-            width: 580,
-            height: 320,
-        },
-        EmbedService {
-            name: "coub",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?coub\.com/view/([a-zA-Z0-9]+)").unwrap(),
-            embed_url_template: "https://coub.com/embed/{}",
-            width: 580,
-            height: 320,
-        },
-        EmbedService {
-            name: "instagram",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?instagram\.com/(?:p|reel)/([a-zA-Z0-9_-]+)").unwrap(),
-            embed_url_template: "https://www.instagram.com/p/{}/embed/",
-            width: 540,
-            height: 663,
-        },
-        EmbedService {
-            name: "twitter",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?(?:twitter\.com|x\.com)/\w+/status/(\d+)").unwrap(),
-            embed_url_template: "https://twitter.com/i/web/status/{}",
-            width: 550,
-            height: 300,
-        },
-        EmbedService {
-            name: "twitch-video",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?twitch\.tv/videos/(\d+)").unwrap(),
-            embed_url_template: "https://player.twitch.tv/?video={}",
-            width: 500,
-            height: 281,
-        },
-        EmbedService {
-            name: "twitch-channel",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?twitch\.tv/([a-zA-Z0-9_]+)(?:/)?$").unwrap(),
-            embed_url_template: "https://player.twitch.tv/?channel={}",
-            width: 500,
-            height: 281,
-        },
-        EmbedService {
-            name: "codepen",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?codepen\.io/([^/]+)/pen/([a-zA-Z0-9]+)").unwrap(),
-            embed_url_template: "https://codepen.io/{}/embed/{}",
-            width: 600,
-            height: 300,
-        },
-        EmbedService {
-            name: "github",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?gist\.github\.com/([^/]+)/([a-zA-Z0-9]+)").unwrap(),
-            embed_url_template: "https://gist.github.com/{}/{}",
-            width: 600,
-            height: 300,
-        },
-        EmbedService {
-            name: "figma",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?figma\.com/(?:file|proto)/([a-zA-Z0-9]+)").unwrap(),
-            embed_url_template: "https://www.figma.com/embed?embed_host=share&url=https://www.figma.com/file/{}",
-            width: 800,
-            height: 450,
-        },
-        EmbedService {
-            name: "miro",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?miro\.com/app/board/([a-zA-Z0-9_-]+)").unwrap(),
-            embed_url_template: "https://miro.com/app/board/{}/",
-            width: 800,
-            height: 600,
-        },
-        EmbedService {
-            name: "imgur",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?imgur\.com/([a-zA-Z0-9]+)").unwrap(),
-            embed_url_template: "https://imgur.com/{}/embed",
-            width: 540,
-            height: 500,
-        },
-        EmbedService {
-            name: "pinterest",
-            regex: Regex::new(r"(?:https?://)?(?:www\.)?pinterest\.com/pin/(\d+)").unwrap(),
-            embed_url_template: "https://www.pinterest.com/pin/{}/",
-            width: 520,
-            height: 600,
-        },
-    ];
+/// Computes the page URL, embed URL and metadata of a URL matched by a
+/// service, for services whose embed URLs cannot be built from templates
+pub type EmbedResolver = fn(&str) -> Option<ResolvedEmbed>;
+
+/// The URLs and metadata of an embed, resolved from a page or embed URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedEmbed {
+    /// Canonical page URL of the embedded content
+    pub source: String,
+    /// URL to load in the embed iframe
+    pub embed: String,
+    pub meta: Option<EmbedMeta>,
 }
 
-/// Detect if a URL matches any known embed service
-pub fn detect_embed_service(url: &str) -> Option<(String, String, u32, u32)> {
+impl ResolvedEmbed {
+    fn new(source: String, embed: String) -> Self {
+        ResolvedEmbed {
+            source,
+            embed,
+            meta: None,
+        }
+    }
+}
+
+/// An embed detected from a URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbedMatch {
+    pub service: String,
+    /// Canonical page URL of the embedded content
+    pub source: String,
+    /// URL to load in the embed iframe
+    pub embed: String,
+    pub width: u32,
+    pub height: u32,
+    pub meta: Option<EmbedMeta>,
+}
+
+impl EmbedMatch {
+    /// Embed block data for the match
+    pub fn into_data(self, caption: Option<String>) -> EmbedData {
+        EmbedData {
+            service: self.service,
+            source: self.source,
+            embed: self.embed,
+            width: self.width,
+            height: self.height,
+            caption,
+            meta: self.meta,
+            title: None,
+            thumbnail_url: None,
+            author_name: None,
+            author_url: None,
+            aspect_ratio: None,
+        }
+    }
+}
+
+impl EmbedService {
+    /// Creates a service from its URL pattern, embed URL template and default size
+    pub fn new(
+        name: &str,
+        pattern: &str,
+        embed_url_template: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<Self> {
+        Ok(EmbedService {
+            name: name.to_string(),
+            regex: compile_pattern(name, pattern)?,
+            embed_url_template: embed_url_template.to_string(),
+            width,
+            height,
+            hosts: Vec::new(),
+            embed_regex: None,
+            source_url_template: None,
+            resolver: None,
+        })
+    }
+
+    /// Restricts the service to URLs on the given hosts
+    pub fn with_hosts(mut self, hosts: &[&str]) -> Self {
+        self.hosts = hosts.iter().map(|host| host.to_lowercase()).collect();
+        self
+    }
+
+    /// Recognizes embed URLs of the service, deriving the canonical page URL
+    /// from the pattern's capture groups with `source_url_template`
+    pub fn with_embed_pattern(mut self, pattern: &str, source_url_template: &str) -> Result<Self> {
+        self.embed_regex = Some(compile_pattern(&self.name, pattern)?);
+        self.source_url_template = Some(source_url_template.to_string());
+        Ok(self)
+    }
+
+    /// Resolves matched URLs with a function instead of the URL templates
+    pub fn with_resolver(mut self, resolver: EmbedResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Whether a URL is on one of the service's hosts
+    pub fn matches_host(&self, url: &str) -> bool {
+        if self.hosts.is_empty() {
+            return true;
+        }
+        url_host(url).is_some_and(|host| {
+            let host = host.strip_prefix("www.").unwrap_or(&host);
+            self.hosts.iter().any(|service_host| service_host == host)
+        })
+    }
+
+    /// Matches a page or embed URL of this service, returning the canonical
+    /// page URL and the embed URL. Returns `None` when the URL does not match or
+    /// an id used by a template was not captured.
+    pub fn match_url(&self, url: &str) -> Option<ResolvedEmbed> {
+        if !self.matches_host(url) {
+            return None;
+        }
+
+        let embed_caps = self.embed_regex.as_ref().and_then(|re| re.captures(url));
+        if let Some(resolver) = self.resolver {
+            if embed_caps.is_none() && !self.regex.is_match(url) {
+                return None;
+            }
+            return resolver(url);
+        }
+
+        if let Some(caps) = embed_caps {
+            let source = match &self.source_url_template {
+                Some(template) => fill_template(template, &caps)?,
+                None => url.to_string(),
+            };
+            return Some(ResolvedEmbed::new(source, url.to_string()));
+        }
+
+        let caps = self.regex.captures(url)?;
+        let embed = fill_template(&self.embed_url_template, &caps)?;
+        Some(ResolvedEmbed::new(url.to_string(), embed))
+    }
+
+    fn to_match(&self, resolved: ResolvedEmbed) -> EmbedMatch {
+        EmbedMatch {
+            service: self.name.clone(),
+            source: resolved.source,
+            embed: resolved.embed,
+            width: self.width,
+            height: self.height,
+            meta: resolved.meta,
+        }
+    }
+}
+
+fn compile_pattern(name: &str, pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| {
+        Error::InvalidInput(format!("invalid pattern for embed service {}: {}", name, e))
+    })
+}
+
+/// Fills the `{}`, `{1}` and `{name}` placeholders of a template with captured
+/// ids, or returns `None` when a placeholder's group did not capture anything
+fn fill_template(template: &str, caps: &Captures) -> Option<String> {
+    let mut next_group = 0;
+    let mut missing = false;
+
+    let filled = PLACEHOLDER.replace_all(template, |cap: &Captures| {
+        let group = match &cap[1] {
+            "" => {
+                next_group += 1;
+                caps.get(next_group)
+            }
+            name => match name.parse::<usize>() {
+                Ok(index) => caps.get(index),
+                Err(_) => caps.name(name),
+            },
+        };
+        match group.map(|m| m.as_str()).filter(|id| !id.is_empty()) {
+            Some(id) => id.to_string(),
+            None => {
+                missing = true;
+                String::new()
+            }
+        }
+    });
+
+    (!missing).then(|| filled.into_owned())
+}
+
+/// Lowercased host of a URL, with or without a scheme
+fn url_host(url: &str) -> Option<String> {
     let url = url.trim();
+    let rest = match url.find("://") {
+        Some(idx) => &url[idx + 3..],
+        None => url.strip_prefix("//").unwrap_or(url),
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.trim_end_matches('.');
+
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
 
-    for service in EMBED_SERVICES.iter() {
-        if let Some(caps) = service.regex.captures(url) {
-            let id = if caps.len() > 2 {
-                // For services with multiple capture groups
-                let mut parts = vec![];
-                for i in 1..caps.len() {
-                    if let Some(m) = caps.get(i) {
-                        parts.push(m.as_str().to_string());
+/// Raw (still percent-encoded) value of a query parameter
+fn raw_query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    let query = url.split('#').next()?.split_once('?')?.1;
+    query
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => Some(value),
+            None if pair == name => Some(""),
+            _ => None,
+        })
+}
+
+/// Percent-decoded value of a query parameter
+fn query_param(url: &str, name: &str) -> Option<String> {
+    raw_query_param(url, name).map(percent_decode)
+}
+
+/// Fragment of a URL, without the `#`
+fn url_fragment(url: &str) -> Option<&str> {
+    url.split_once('#').map(|(_, fragment)| fragment)
+}
+
+/// Percent-encodes everything but unreserved characters, for URLs passed as
+/// query parameters
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
                     }
+                    Err(_) => decoded.push(b'%'),
                 }
-                parts.join("/")
-            } else {
-                // For services with single capture group
-                caps.get(1)
-                    .map(|m| m.as_str().to_string())
-                    .unwrap_or_default()
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn default_services() -> Result<Vec<EmbedService>> {
+    let mut services = vec![
+        EmbedService::new(
+            "youtube",
+            video::YOUTUBE_PATTERN,
+            "https://www.youtube.com/embed/{}",
+            580,
+            320,
+        )?
+        .with_hosts(&[
+            "youtube.com",
+            "m.youtube.com",
+            "youtu.be",
+            "youtube-nocookie.com",
+        ])
+        .with_embed_pattern(
+            video::YOUTUBE_EMBED_PATTERN,
+            "https://www.youtube.com/watch?v={}",
+        )?
+        .with_resolver(video::resolve_youtube),
+        EmbedService::new(
+            "vimeo",
+            video::VIMEO_PATTERN,
+            "https://player.vimeo.com/video/{}",
+            580,
+            320,
+        )?
+        .with_hosts(&["vimeo.com", "player.vimeo.com"])
+        .with_embed_pattern(video::VIMEO_EMBED_PATTERN, "https://vimeo.com/{}")?
+        .with_resolver(video::resolve_vimeo),
+        EmbedService::new(
+            "coub",
+            r"(?:https?://)?(?:www\.)?coub\.com/view/([a-zA-Z0-9]+)",
+            "https://coub.com/embed/{}",
+            580,
+            320,
+        )?
+        .with_hosts(&["coub.com"])
+        .with_embed_pattern(r"coub\.com/embed/([a-zA-Z0-9]+)", "https://coub.com/view/{}")?,
+        EmbedService::new(
+            "instagram",
+            r"(?:https?://)?(?:www\.)?instagram\.com/(?:p|reel)/([a-zA-Z0-9_-]+)",
+            "https://www.instagram.com/p/{}/embed/",
+            540,
+            663,
+        )?
+        .with_hosts(&["instagram.com"])
+        .with_embed_pattern(
+            r"instagram\.com/(?:p|reel)/([a-zA-Z0-9_-]+)/embed",
+            "https://www.instagram.com/p/{}/",
+        )?,
+        EmbedService::new(
+            "twitter",
+            r"(?:https?://)?(?:www\.)?(?:twitter\.com|x\.com)/\w+/status/(\d+)",
+            "https://twitter.com/i/web/status/{}",
+            550,
+            300,
+        )?
+        .with_hosts(&[
+            "twitter.com",
+            "mobile.twitter.com",
+            "platform.twitter.com",
+            "x.com",
+        ])
+        .with_embed_pattern(
+            r"(?:platform\.twitter\.com/embed/Tweet\.html\?(?:.*&)?id=|twitter\.com/i/web/status/)(\d+)",
+            "https://twitter.com/i/web/status/{}",
+        )?,
+        EmbedService::new(
+            "twitch-video",
+            r"(?:https?://)?(?:www\.)?twitch\.tv/videos/(\d+)",
+            "https://player.twitch.tv/?video={}",
+            500,
+            281,
+        )?
+        .with_hosts(&["twitch.tv", "m.twitch.tv", "player.twitch.tv"])
+        .with_embed_pattern(
+            r"player\.twitch\.tv/\?(?:.*&)?video=v?(\d+)",
+            "https://www.twitch.tv/videos/{}",
+        )?,
+        EmbedService::new(
+            "twitch-channel",
+            r"(?:https?://)?(?:www\.)?twitch\.tv/([a-zA-Z0-9_]+)(?:/)?$",
+            "https://player.twitch.tv/?channel={}",
+            500,
+            281,
+        )?
+        .with_hosts(&["twitch.tv", "m.twitch.tv", "player.twitch.tv"])
+        .with_embed_pattern(
+            r"player\.twitch\.tv/\?(?:.*&)?channel=([a-zA-Z0-9_]+)",
+            "https://www.twitch.tv/{}",
+        )?,
+        EmbedService::new(
+            "codepen",
+            r"(?:https?://)?(?:www\.)?codepen\.io/([^/]+)/pen/([a-zA-Z0-9]+)",
+            "https://codepen.io/{}/embed/{}",
+            600,
+            300,
+        )?
+        .with_hosts(&["codepen.io"])
+        .with_embed_pattern(
+            r"codepen\.io/([^/]+)/embed/(?:preview/)?([a-zA-Z0-9]+)",
+            "https://codepen.io/{}/pen/{}",
+        )?,
+        EmbedService::new(
+            "github",
+            r"(?:https?://)?(?:www\.)?gist\.github\.com/([^/]+)/([a-zA-Z0-9]+)",
+            "https://gist.github.com/{}/{}",
+            600,
+            300,
+        )?
+        .with_hosts(&["gist.github.com"]),
+        EmbedService::new(
+            "figma",
+            r"(?:https?://)?(?:www\.)?figma\.com/(?:file|proto)/([a-zA-Z0-9]+)",
+            "https://www.figma.com/embed?embed_host=share&url=https://www.figma.com/file/{}",
+            800,
+            450,
+        )?
+        .with_hosts(&["figma.com"])
+        .with_embed_pattern(
+            r"figma\.com/embed\?(?:.*&)?url=https?(?::|%3A)(?:/|%2F){2}(?:www\.)?figma\.com(?:/|%2F)(?:file|proto)(?:/|%2F)([a-zA-Z0-9]+)",
+            "https://www.figma.com/file/{}",
+        )?,
+        EmbedService::new(
+            "miro",
+            r"(?:https?://)?(?:www\.)?miro\.com/app/board/([a-zA-Z0-9_=-]+)",
+            "https://miro.com/app/board/{}/",
+            800,
+            600,
+        )?
+        .with_hosts(&["miro.com"])
+        .with_embed_pattern(
+            r"miro\.com/app/live-embed/([a-zA-Z0-9_=-]+)",
+            "https://miro.com/app/board/{}/",
+        )?,
+        EmbedService::new(
+            "imgur",
+            r"(?:https?://)?(?:www\.)?imgur\.com/([a-zA-Z0-9]+)",
+            "https://imgur.com/{}/embed",
+            540,
+            500,
+        )?
+        .with_hosts(&["imgur.com", "i.imgur.com"])
+        .with_embed_pattern(r"imgur\.com/([a-zA-Z0-9]+)/embed", "https://imgur.com/{}")?,
+        EmbedService::new(
+            "pinterest",
+            r"(?:https?://)?(?:www\.)?pinterest\.com/pin/(\d+)",
+            "https://www.pinterest.com/pin/{}/",
+            520,
+            600,
+        )?
+        .with_hosts(&["pinterest.com", "assets.pinterest.com"])
+        .with_embed_pattern(
+            r"assets\.pinterest\.com/ext/embed\.html\?(?:.*&)?id=(\d+)",
+            "https://www.pinterest.com/pin/{}/",
+        )?,
+        EmbedService::new(
+            "spotify",
+            r"(?:https?://)?open\.spotify\.com/(?:intl-[a-z-]+/)?(track|episode|show|album|playlist|artist)/([a-zA-Z0-9]+)",
+            "https://open.spotify.com/embed/{}/{}",
+            580,
+            352,
+        )?
+        .with_hosts(&["open.spotify.com"])
+        .with_embed_pattern(
+            r"open\.spotify\.com/embed(?:-podcast)?/(track|episode|show|album|playlist|artist)/([a-zA-Z0-9]+)",
+            "https://open.spotify.com/{}/{}",
+        )?,
+        EmbedService::new(
+            "soundcloud",
+            r"(?:https?://)?(?:www\.|m\.)?soundcloud\.com/([\w-]+/(?:sets/)?[\w-]+)",
+            "https://w.soundcloud.com/player/?url=https%3A//soundcloud.com/{}",
+            580,
+            166,
+        )?
+        .with_hosts(&["soundcloud.com", "m.soundcloud.com", "w.soundcloud.com"])
+        .with_embed_pattern(
+            r"w\.soundcloud\.com/player/?\?(?:.*&)?url=https?(?::|%3A)(?://|%2F%2F)soundcloud\.com(?:/|%2F)([\w-]+)(?:/|%2F)((?:sets(?:/|%2F))?[\w-]+)",
+            "https://soundcloud.com/{}/{}",
+        )?,
+        EmbedService::new(
+            "apple-podcasts",
+            r"(?:https?://)?podcasts\.apple\.com/([a-z]{2}/podcast/[\w%.-]+/id\d+(?:\?i=\d+)?)",
+            "https://embed.podcasts.apple.com/{}",
+            660,
+            175,
+        )?
+        .with_hosts(&["podcasts.apple.com", "embed.podcasts.apple.com"])
+        .with_embed_pattern(
+            r"embed\.podcasts\.apple\.com/([a-z]{2}/podcast/[\w%.-]+/id\d+(?:\?i=\d+)?)",
+            "https://podcasts.apple.com/{}",
+        )?,
+        // Bandcamp page URLs don't contain the album or track id the player
+        // needs, so only player URLs are recognized
+        EmbedService::new(
+            "bandcamp",
+            r#"(?:https?://)?bandcamp\.com/EmbeddedPlayer/((?:[\w-]+=[\w.-]+/)*(?:album|track)=\d+[^?#"'\s]*)"#,
+            "https://bandcamp.com/EmbeddedPlayer/{}",
+            350,
+            120,
+        )?
+        .with_hosts(&["bandcamp.com"]),
+    ];
+    services.extend(maps::map_services()?);
+    services.extend(social::social_services()?);
+
+    Ok(services)
+}
+
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{(\w*)\}").unwrap();
+    static ref HTML_ATTRIBUTE: Regex = Regex::new(
+        r#"([a-zA-Z_:][\w:.-]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#
+    )
+    .unwrap();
+    static ref CSS_LENGTH: Regex = Regex::new(r"^(\d+(?:\.\d+)?)\s*([a-z%]*)$").unwrap();
+    static ref RATIO_CLASS: Regex =
+        Regex::new(r"^(?:ratio-|embed-responsive-|aspect-\[)(\d+)(?:x|by|/)(\d+)\]?$").unwrap();
+    static ref DEFAULT_REGISTRY: EmbedRegistry = EmbedRegistry::default();
+    static ref DEFAULT_SERVICES: Vec<EmbedService> = default_services().unwrap();
+}
+
+/// The embed services recognized during conversion, checked in order. The
+/// default registry holds the built-in services; custom services can be
+/// registered in code or loaded from a JSON or TOML config.
+#[derive(Debug, Clone)]
+pub struct EmbedRegistry {
+    services: Vec<EmbedService>,
+}
+
+impl Default for EmbedRegistry {
+    fn default() -> Self {
+        EmbedRegistry {
+            services: DEFAULT_SERVICES.clone(),
+        }
+    }
+}
+
+impl EmbedRegistry {
+    /// Creates a registry without any services
+    pub fn empty() -> Self {
+        EmbedRegistry {
+            services: Vec::new(),
+        }
+    }
+
+    /// Registers a service. It replaces a service with the same name, and
+    /// otherwise takes precedence over the services registered before it.
+    pub fn register(&mut self, service: EmbedService) -> &mut Self {
+        match self.services.iter().position(|s| s.name == service.name) {
+            Some(idx) => self.services[idx] = service,
+            None => self.services.insert(0, service),
+        }
+        self
+    }
+
+    /// The registered services, in the order they are checked
+    pub fn services(&self) -> &[EmbedService] {
+        &self.services
+    }
+
+    /// Detect if a page or embed URL matches any registered embed service
+    pub fn detect(&self, url: &str) -> Option<EmbedMatch> {
+        let url = url.trim();
+
+        self.services
+            .iter()
+            .find_map(|service| service.match_url(url).map(|urls| service.to_match(urls)))
+    }
+
+    /// Detect the embed service of an iframe `src`. Sources that match no
+    /// pattern still belong to a service whose hosts they are on, and are then
+    /// used as both the source and the embed URL.
+    pub fn detect_iframe(&self, src: &str) -> Option<EmbedMatch> {
+        let src = src.trim();
+
+        self.detect(src).or_else(|| {
+            self.services
+                .iter()
+                .find(|service| !service.hosts.is_empty() && service.matches_host(src))
+                .map(|service| {
+                    service.to_match(ResolvedEmbed::new(src.to_string(), src.to_string()))
+                })
+        })
+    }
+
+    /// Turns map short links (`maps.app.goo.gl`) that stand alone in a
+    /// paragraph or link preview into embeds. Short links carry no map view,
+    /// so they are expanded through `resolver` first; links that fail to
+    /// resolve or resolve to no service are left unchanged.
+    pub fn expand_short_links(
+        &self,
+        blocks: &mut [EditorJsBlock],
+        resolver: &impl ShortLinkResolver,
+    ) {
+        for block in blocks {
+            let url = match block {
+                EditorJsBlock::Paragraph { data } => standalone_link(&data.text),
+                EditorJsBlock::LinkTool { data } => Some(data.link.clone()),
+                EditorJsBlock::Toggle { children, .. } => {
+                    self.expand_short_links(children, resolver);
+                    continue;
+                }
+                EditorJsBlock::Tuned { block, .. } => {
+                    self.expand_short_links(std::slice::from_mut(block.as_mut()), resolver);
+                    continue;
+                }
+                _ => None,
             };
+            let Some(url) = url.filter(|url| maps::is_short_link(url)) else {
+                continue;
+            };
+
+            if let Some(embed) = resolver
+                .resolve(&url)
+                .ok()
+                .and_then(|target| self.detect(&target))
+            {
+                *block = EditorJsBlock::Embed {
+                    data: embed.into_data(None),
+                };
+            }
+        }
+    }
+
+    /// Loads a registry from a JSON config:
+    ///
+    /// ```json
+    /// {
+    ///   "defaults": true,
+    ///   "services": [
+    ///     {"name": "trailmap", "pattern": "maps\\.example\\.com/t/(\\w+)",
+    ///      "embed_url": "https://maps.example.com/embed/{}", "width": 800, "height": 600}
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// `defaults` (default `true`) keeps the built-in services; `width` and
+    /// `height` default to 580×320. Optional `hosts` restrict the service to
+    /// URLs on those hosts, and `embed_pattern` with `source_url` recognize
+    /// embed URLs (see [`EmbedService::with_embed_pattern`]).
+    pub fn from_json(config: &str) -> Result<Self> {
+        let config: EmbedRegistryConfig = serde_json::from_str(config)
+            .map_err(|e| Error::InvalidInput(format!("invalid embed config: {}", e)))?;
+        config.into_registry()
+    }
+
+    /// Loads a registry from a TOML config with the same fields as
+    /// [`EmbedRegistry::from_json`], using `[[services]]` tables
+    pub fn from_toml(config: &str) -> Result<Self> {
+        let config: EmbedRegistryConfig = toml::from_str(config)
+            .map_err(|e| Error::InvalidInput(format!("invalid embed config: {}", e)))?;
+        config.into_registry()
+    }
+
+    /// Loads a registry from a `.json` or `.toml` config file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidInput(format!(
+                "cannot read embed config {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::from_toml(&config),
+            _ => Self::from_json(&config),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct EmbedRegistryConfig {
+    #[serde(default = "default_true")]
+    defaults: bool,
+    #[serde(default)]
+    services: Vec<EmbedServiceConfig>,
+}
+
+#[derive(serde::Deserialize)]
+struct EmbedServiceConfig {
+    name: String,
+    pattern: String,
+    embed_url: String,
+    #[serde(default = "default_width")]
+    width: u32,
+    #[serde(default = "default_height")]
+    height: u32,
+    #[serde(default)]
+    hosts: Vec<String>,
+    embed_pattern: Option<String>,
+    source_url: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_width() -> u32 {
+    580
+}
+
+fn default_height() -> u32 {
+    320
+}
+
+impl EmbedRegistryConfig {
+    fn into_registry(self) -> Result<EmbedRegistry> {
+        let mut registry = if self.defaults {
+            EmbedRegistry::default()
+        } else {
+            EmbedRegistry::empty()
+        };
+
+        for config in self.services {
+            let hosts: Vec<&str> = config.hosts.iter().map(String::as_str).collect();
+            let mut service = EmbedService::new(
+                &config.name,
+                &config.pattern,
+                &config.embed_url,
+                config.width,
+                config.height,
+            )?
+            .with_hosts(&hosts);
 
-            if !id.is_empty() {
-                let embed_url = service.embed_url_template.replace("{}", &id);
-                return Some((
-                    service.name.to_string(),
-                    embed_url,
-                    service.width,
-                    service.height,
-                ));
+            if let Some(pattern) = &config.embed_pattern {
+                let source_url = config.source_url.as_deref().ok_or_else(|| {
+                    Error::InvalidInput(format!(
+                        "embed service {} has an embed_pattern without a source_url",
+                        config.name
+                    ))
+                })?;
+                service = service.with_embed_pattern(pattern, source_url)?;
             }
+            registry.register(service);
         }
+        Ok(registry)
     }
+}
 
-    None
+/// Detect if a URL matches any of the built-in embed services, returning the
+/// service name, embed URL and default width and height
+pub fn detect_embed_service(url: &str) -> Option<(String, String, u32, u32)> {
+    DEFAULT_REGISTRY
+        .detect(url)
+        .map(|embed| (embed.service, embed.embed, embed.width, embed.height))
 }
 
 /// Parse an iframe tag and extract embed information
 pub fn parse_iframe(attrs: &str) -> Option<(String, u32, u32)> {
     let src_re = Regex::new(r#"src=["']?([^"'\s>]+)["']?"#).ok()?;
-    let width_re = Regex::new(r#"width=["']?(\d+)["']?"#).ok()?;
-    let height_re = Regex::new(r#"height=["']?(\d+)["']?"#).ok()?;
 
     let src = src_re
         .captures(attrs)
         .and_then(|cap| cap.get(1))
         .map(|m| m.as_str())?;
 
-    let width = width_re
-        .captures(attrs)
-        .and_then(|cap| cap.get(1))
-        .and_then(|m| m.as_str().parse().ok())
-        .unwrap_or(580);
-
-    let height = height_re
-        .captures(attrs)
-        .and_then(|cap| cap.get(1))
-        .and_then(|m| m.as_str().parse().ok())
-        .unwrap_or(320);
+    let (width, height) = EmbedSize::from_attrs(attrs).resolve(580, 320);
 
     Some((src.to_string(), width, height))
 }
 
-/// Detect the embed service from an iframe src URL
-pub fn detect_service_from_src(src: &str) -> Option<String> {
-    if src.contains("youtube.com") || src.contains("youtu.be") {
-        Some("youtube".to_string())
-    } else if src.contains("vimeo.com") {
-        Some("vimeo".to_string())
-    } else if src.contains("coub.com") {
-        Some("coub".to_string())
-    } else if src.contains("instagram.com") {
-        Some("instagram".to_string())
-    } else if src.contains("twitter.com") || src.contains("x.com") {
-        Some("twitter".to_string())
-    } else if src.contains("twitch.tv") {
-        if src.contains("video=") {
-            Some("twitch-video".to_string())
-        } else {
-            Some("twitch-channel".to_string())
+/// Pixel size and aspect ratio of an embed, from HTML attributes and CSS.
+/// Relative sizes such as `100%` or `50vw` leave the dimension unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EmbedSize {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Width divided by height
+    pub aspect_ratio: Option<f64>,
+}
+
+impl EmbedSize {
+    /// Reads the `width` and `height` attributes, and `width`, `height` and
+    /// `aspect-ratio` in the inline style, which take precedence
+    pub fn from_attrs(attrs: &str) -> Self {
+        let mut size = EmbedSize::default();
+        let attributes = html_attributes(attrs);
+
+        for (name, value) in &attributes {
+            match name.as_str() {
+                "width" => size.width = css_length(value),
+                "height" => size.height = css_length(value),
+                _ => {}
+            }
+        }
+
+        let style = attributes.iter().find(|(name, _)| name == "style");
+        for (property, value) in style.map_or(Vec::new(), |(_, style)| css_declarations(style)) {
+            match property.as_str() {
+                "width" => size.width = css_length(&value).or(size.width),
+                "height" => size.height = css_length(&value).or(size.height),
+                "aspect-ratio" => size.aspect_ratio = parse_ratio(&value),
+                _ => {}
+            }
+        }
+
+        size
+    }
+
+    /// Aspect ratio set by a responsive wrapper element around an embed:
+    /// `aspect-ratio`, a percentage `padding-bottom` or `padding-top`, or a
+    /// Bootstrap (`ratio-16x9`, `embed-responsive-16by9`) or Tailwind
+    /// (`aspect-video`, `aspect-[4/3]`) class
+    pub fn wrapper_ratio(attrs: &str) -> Option<f64> {
+        let attributes = html_attributes(attrs);
+
+        let style = attributes.iter().find(|(name, _)| name == "style");
+        for (property, value) in style.map_or(Vec::new(), |(_, style)| css_declarations(style)) {
+            let padding = match property.as_str() {
+                "aspect-ratio" => return parse_ratio(&value),
+                "padding-bottom" | "padding-top" => Some(value.as_str()),
+                "padding" => value.split_whitespace().next(),
+                _ => None,
+            };
+            let percent = padding
+                .and_then(|padding| padding.trim().strip_suffix('%'))
+                .and_then(|percent| percent.trim().parse::<f64>().ok())
+                .filter(|percent| *percent > 0.0);
+            if let Some(percent) = percent {
+                return Some(round_ratio(100.0 / percent));
+            }
         }
-    } else if src.contains("codepen.io") {
-        Some("codepen".to_string())
-    } else if src.contains("gist.github.com") {
-        Some("github".to_string())
-    } else if src.contains("figma.com") {
-        Some("figma".to_string())
-    } else if src.contains("miro.com") {
-        Some("miro".to_string())
-    } else if src.contains("imgur.com") {
-        Some("imgur".to_string())
-    } else if src.contains("pinterest.com") {
-        Some("pinterest".to_string())
-    } else {
-        None
+
+        let class = attributes.iter().find(|(name, _)| name == "class")?;
+        class.1.split_whitespace().find_map(|class| {
+            let ratio = match class {
+                "aspect-video" => "16/9",
+                "aspect-square" => "1",
+                _ => {
+                    let caps = RATIO_CLASS.captures(class)?;
+                    return parse_ratio(&format!("{}/{}", &caps[1], &caps[2]));
+                }
+            };
+            parse_ratio(ratio)
+        })
     }
+
+    /// Aspect ratio, falling back to the ratio of the pixel dimensions
+    pub fn ratio(&self) -> Option<f64> {
+        self.aspect_ratio.or(match (self.width, self.height) {
+            (Some(width), Some(height)) if height > 0 => {
+                Some(round_ratio(width as f64 / height as f64))
+            }
+            _ => None,
+        })
+    }
+
+    /// Takes the aspect ratio of the default size when only one dimension is
+    /// known, so that the other is derived from it rather than distorting the
+    /// embed
+    pub fn with_default_ratio(self, default_width: u32, default_height: u32) -> Self {
+        let partial = self.width.is_some() != self.height.is_some();
+        if !partial || self.aspect_ratio.is_some() || default_height == 0 {
+            return self;
+        }
+        EmbedSize {
+            aspect_ratio: Some(round_ratio(default_width as f64 / default_height as f64)),
+            ..self
+        }
+    }
+
+    /// Pixel dimensions, deriving a missing one from the aspect ratio (of the
+    /// defaults, if the size has none) and otherwise using the defaults
+    pub fn resolve(&self, default_width: u32, default_height: u32) -> (u32, u32) {
+        let scale = |value: u32, factor: f64| (value as f64 * factor).round() as u32;
+        let size = self.with_default_ratio(default_width, default_height);
+        match (size.width, size.height, size.aspect_ratio) {
+            (Some(width), Some(height), _) => (width, height),
+            (Some(width), None, Some(ratio)) => (width, scale(width, 1.0 / ratio)),
+            (None, Some(height), Some(ratio)) => (scale(height, ratio), height),
+            (None, None, Some(ratio)) => (default_width, scale(default_width, 1.0 / ratio)),
+            _ => (default_width, default_height),
+        }
+    }
+}
+
+/// Lowercased names and values of HTML attributes. Boolean attributes such
+/// as `controls` have an empty value.
+pub(crate) fn html_attributes(attrs: &str) -> Vec<(String, String)> {
+    HTML_ATTRIBUTE
+        .captures_iter(attrs)
+        .map(|caps| {
+            let value = caps
+                .get(2)
+                .or_else(|| caps.get(3))
+                .or_else(|| caps.get(4))
+                .map_or("", |value| value.as_str());
+            (caps[1].to_lowercase(), value.to_string())
+        })
+        .collect()
+}
+
+fn css_declarations(style: &str) -> Vec<(String, String)> {
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| {
+            let value = value.trim().trim_end_matches("!important").trim();
+            (property.trim().to_lowercase(), value.to_lowercase())
+        })
+        .collect()
+}
+
+/// Length in pixels; relative lengths have no fixed pixel size
+fn css_length(value: &str) -> Option<u32> {
+    let caps = CSS_LENGTH.captures(value.trim())?;
+    let number: f64 = caps[1].parse().ok()?;
+    let pixels = match caps.get(2).map_or("", |unit| unit.as_str()) {
+        "" | "px" => number,
+        "em" | "rem" => number * 16.0,
+        "pt" => number * 4.0 / 3.0,
+        _ => return None,
+    };
+    (pixels >= 1.0).then(|| pixels.round() as u32)
+}
+
+/// `16/9`, `16 / 9`, `1.7778` or `auto 16/9`
+fn parse_ratio(value: &str) -> Option<f64> {
+    let value = value.trim().trim_start_matches("auto").trim();
+    let ratio = match value.split_once('/') {
+        Some((width, height)) => {
+            width.trim().parse::<f64>().ok()? / height.trim().parse::<f64>().ok()?
+        }
+        None => value.parse().ok()?,
+    };
+    (ratio.is_finite() && ratio > 0.0).then(|| round_ratio(ratio))
+}
+
+fn round_ratio(ratio: f64) -> f64 {
+    (ratio * 10_000.0).round() / 10_000.0
+}
+
+/// Detect the built-in embed service from an iframe src URL
+pub fn detect_service_from_src(src: &str) -> Option<String> {
+    DEFAULT_REGISTRY
+        .detect_iframe(src)
+        .map(|embed| embed.service)
 }
 
 #[cfg(test)]
@@ -288,4 +1027,283 @@ mod tests {
         let result = detect_service_from_src(src);
         assert_eq!(result, Some("youtube".to_string()));
     }
+
+    #[test]
+    fn test_embed_url_template_placeholders() {
+        let (_, embed_url, _, _) =
+            detect_embed_service("https://codepen.io/anna/pen/xYzAbc").unwrap();
+        assert_eq!(embed_url, "https://codepen.io/anna/embed/xYzAbc");
+
+        let service = EmbedService::new(
+            "trail",
+            r"example\.com/(?P<region>\w+)/(\d+)",
+            "https://example.com/embed/{2}?region={region}",
+            600,
+            400,
+        )
+        .unwrap();
+        let resolved = service.match_url("https://example.com/lapland/42").unwrap();
+        assert_eq!(resolved.source, "https://example.com/lapland/42");
+        assert_eq!(
+            resolved.embed,
+            "https://example.com/embed/42?region=lapland"
+        );
+        assert_eq!(service.match_url("https://example.com/about"), None);
+    }
+
+    #[test]
+    fn test_registry_custom_services() {
+        let mut registry = EmbedRegistry::default();
+        registry.register(
+            EmbedService::new(
+                "trailmap",
+                r"maps\.example\.com/t/(\w+)",
+                "https://maps.example.com/embed/{}",
+                800,
+                600,
+            )
+            .unwrap(),
+        );
+
+        let embed = registry
+            .detect("https://maps.example.com/t/kungsleden")
+            .unwrap();
+        assert_eq!(embed.service, "trailmap");
+        assert_eq!(embed.embed, "https://maps.example.com/embed/kungsleden");
+        assert_eq!((embed.width, embed.height), (800, 600));
+        assert!(registry.detect("https://vimeo.com/123456789").is_some());
+        assert!(detect_embed_service("https://maps.example.com/t/kungsleden").is_none());
+    }
+
+    #[test]
+    fn test_registry_from_config() {
+        let json = r#"{"defaults": false, "services": [{"name": "trailmap", "pattern": "maps\\.example\\.com/t/(\\w+)", "embed_url": "https://maps.example.com/embed/{}"}]}"#;
+        let registry = EmbedRegistry::from_json(json).unwrap();
+        assert_eq!(registry.services().len(), 1);
+        let embed = registry.detect("https://maps.example.com/t/a1").unwrap();
+        assert_eq!((embed.width, embed.height), (580, 320));
+        assert!(registry.detect("https://vimeo.com/123456789").is_none());
+
+        let toml = "[[services]]\nname = \"youtube\"\npattern = 'videos\\.example\\.com/(\\d+)'\nembed_url = \"https://videos.example.com/embed/{}\"\nheight = 400\n";
+        let registry = EmbedRegistry::from_toml(toml).unwrap();
+        // A service with the name of a built-in service replaces it
+        assert_eq!(registry.services().len(), DEFAULT_SERVICES.len());
+        let embed = registry.detect("https://videos.example.com/7").unwrap();
+        assert_eq!((embed.service.as_str(), embed.height), ("youtube", 400));
+
+        assert!(EmbedRegistry::from_json(
+            r#"{"services": [{"name": "x", "pattern": "(", "embed_url": ""}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_embed_size_from_attrs() {
+        let size = EmbedSize::from_attrs(r#"src="x" width="100%" height="315""#);
+        assert_eq!((size.width, size.height), (None, Some(315)));
+
+        let size = EmbedSize::from_attrs(
+            r#"width="560" style="width: 40rem; height: 50vh; aspect-ratio: 16 / 9 !important""#,
+        );
+        assert_eq!((size.width, size.height), (Some(640), None));
+        assert_eq!(size.aspect_ratio, Some(1.7778));
+        assert_eq!(size.resolve(580, 320), (640, 360));
+
+        let size = EmbedSize::from_attrs(r#"width=560 height='315px' data-width="9""#);
+        assert_eq!(size.resolve(580, 320), (560, 315));
+        assert_eq!(size.ratio(), Some(1.7778));
+
+        assert_eq!(EmbedSize::default().resolve(580, 320), (580, 320));
+        assert_eq!(
+            parse_iframe(r#"src="https://example.com/e" width="100%" height="400""#),
+            Some(("https://example.com/e".to_string(), 725, 400))
+        );
+    }
+
+    #[test]
+    fn test_wrapper_ratio() {
+        let cases = [
+            (
+                r#"style="position:relative;padding-bottom:56.25%;height:0""#,
+                Some(1.7778),
+            ),
+            (r#"style="padding: 75% 0 0 0""#, Some(1.3333)),
+            (r#"style="aspect-ratio: 1""#, Some(1.0)),
+            (r#"class="ratio ratio-4x3""#, Some(1.3333)),
+            (
+                r#"class="embed-responsive embed-responsive-21by9""#,
+                Some(2.3333),
+            ),
+            (r#"class="w-full aspect-video""#, Some(1.7778)),
+            (r#"class="aspect-[9/16]""#, Some(0.5625)),
+            (r#"class="wrapper" style="padding: 1em""#, None),
+        ];
+
+        for (attrs, ratio) in cases {
+            assert_eq!(EmbedSize::wrapper_ratio(attrs), ratio, "{}", attrs);
+        }
+    }
+
+    #[test]
+    fn test_audio_services() {
+        let registry = EmbedRegistry::default();
+        let cases = [
+            (
+                "https://open.spotify.com/intl-sv/track/6rqhFgbbKwnb9MLmUQDhG6?si=abc",
+                "spotify",
+                "https://open.spotify.com/embed/track/6rqhFgbbKwnb9MLmUQDhG6",
+                352,
+            ),
+            (
+                "https://soundcloud.com/trailcast/sets/season-2",
+                "soundcloud",
+                "https://w.soundcloud.com/player/?url=https%3A//soundcloud.com/trailcast/sets/season-2",
+                166,
+            ),
+            (
+                "https://podcasts.apple.com/se/podcast/trail-talk/id1234567890",
+                "apple-podcasts",
+                "https://embed.podcasts.apple.com/se/podcast/trail-talk/id1234567890",
+                175,
+            ),
+            (
+                "https://bandcamp.com/EmbeddedPlayer/album=1234567/size=large/tracklist=false/",
+                "bandcamp",
+                "https://bandcamp.com/EmbeddedPlayer/album=1234567/size=large/tracklist=false/",
+                120,
+            ),
+        ];
+
+        for (url, service, embed, height) in cases {
+            let detected = registry.detect(url).unwrap();
+            assert_eq!(detected.service, service);
+            assert_eq!(detected.embed, embed);
+            assert_eq!(detected.height, height);
+        }
+
+        assert!(registry
+            .detect("https://trailband.bandcamp.com/album/north")
+            .is_none());
+        assert_eq!(
+            detect_service_from_src("https://bandcamp.com/EmbeddedPlayer/v=2/track=42/"),
+            Some("bandcamp".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hosts_are_matched_exactly() {
+        assert!(detect_embed_service("https://dropbox.com/user/status/1234567890").is_none());
+        assert!(detect_embed_service("https://example.com/?next=youtu.be/dQw4w9WgXcQ").is_none());
+        assert!(
+            detect_embed_service("https://mobile.twitter.com/user/status/1234567890").is_some()
+        );
+        assert_eq!(
+            detect_service_from_src("https://example.x.com/embed/1"),
+            None
+        );
+        assert_eq!(
+            detect_service_from_src("https://www.dropbox.com/s/abc"),
+            None
+        );
+        assert_eq!(
+            url_host("HTTPS://user@Www.YouTube.com:443/watch").as_deref(),
+            Some("www.youtube.com")
+        );
+    }
+
+    #[test]
+    fn test_embed_urls_map_to_source_urls() {
+        let registry = EmbedRegistry::default();
+        let cases = [
+            (
+                "https://open.spotify.com/embed/episode/4rOoJ6Egrf8K2IrywzwOMk?utm_source=generator",
+                "spotify",
+                "https://open.spotify.com/episode/4rOoJ6Egrf8K2IrywzwOMk",
+            ),
+            (
+                "https://w.soundcloud.com/player/?url=https%3A//soundcloud.com/trailcast/episode-12&color=%23ff5500",
+                "soundcloud",
+                "https://soundcloud.com/trailcast/episode-12",
+            ),
+            (
+                "https://embed.podcasts.apple.com/us/podcast/trail-talk/id1234567890?i=1000612345678",
+                "apple-podcasts",
+                "https://podcasts.apple.com/us/podcast/trail-talk/id1234567890?i=1000612345678",
+            ),
+            (
+                "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ",
+                "youtube",
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            (
+                "https://player.vimeo.com/video/123456789",
+                "vimeo",
+                "https://vimeo.com/123456789",
+            ),
+            (
+                "https://player.twitch.tv/?video=v987654&parent=example.com",
+                "twitch-video",
+                "https://www.twitch.tv/videos/987654",
+            ),
+            (
+                "https://player.twitch.tv/?channel=trailrunner&parent=example.com",
+                "twitch-channel",
+                "https://www.twitch.tv/trailrunner",
+            ),
+            (
+                "https://codepen.io/anna/embed/preview/xYzAbc",
+                "codepen",
+                "https://codepen.io/anna/pen/xYzAbc",
+            ),
+            (
+                "https://platform.twitter.com/embed/Tweet.html?id=1234567890",
+                "twitter",
+                "https://twitter.com/i/web/status/1234567890",
+            ),
+        ];
+
+        for (src, service, source) in cases {
+            let embed = registry.detect_iframe(src).unwrap();
+            assert_eq!(embed.service, service, "{}", src);
+            assert_eq!(embed.source, source, "{}", src);
+            assert_eq!(embed.embed, src);
+        }
+
+        // Sources on a service's host that match no pattern keep the src
+        let embed = registry
+            .detect_iframe("https://www.youtube.com/embed?listType=playlist&list=PL123")
+            .unwrap();
+        assert_eq!(embed.service, "youtube");
+        assert_eq!(embed.source, embed.embed);
+        assert!(registry
+            .detect("https://www.youtube.com/embed?listType=playlist&list=PL123")
+            .is_none());
+    }
+
+    #[test]
+    fn test_config_hosts_and_embed_pattern() {
+        let toml = r#"
+[[services]]
+name = "trailmap"
+hosts = ["maps.example.com"]
+pattern = 'maps\.example\.com/t/(\w+)'
+embed_url = "https://maps.example.com/embed/{}"
+embed_pattern = 'maps\.example\.com/embed/(\w+)'
+source_url = "https://maps.example.com/t/{}"
+"#;
+        let registry = EmbedRegistry::from_toml(toml).unwrap();
+
+        let embed = registry
+            .detect_iframe("https://maps.example.com/embed/kungsleden?zoom=9")
+            .unwrap();
+        assert_eq!(embed.service, "trailmap");
+        assert_eq!(embed.source, "https://maps.example.com/t/kungsleden");
+        assert!(registry
+            .detect("https://mirror.example.org/?u=maps.example.com/t/kungsleden")
+            .is_none());
+
+        let missing_source =
+            "[[services]]\nname = \"x\"\npattern = 'x'\nembed_url = 'x'\nembed_pattern = 'y'\n";
+        assert!(EmbedRegistry::from_toml(missing_source).is_err());
+    }
 }
@@ -0,0 +1,547 @@
+//! Map embed services: OpenStreetMap, Google Maps, uMap and Mapillary
+
+use super::{
+    percent_encode, query_param, raw_query_param, url_fragment, EmbedService, ResolvedEmbed,
+};
+use crate::error::{Error, Result};
+use crate::models::EmbedMeta;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Size of the map viewport assumed when converting between zoom levels and
+/// bounding boxes
+const VIEWPORT: (f64, f64) = (600.0, 450.0);
+
+/// Zoom level used when a link only marks a location
+const DEFAULT_ZOOM: f64 = 15.0;
+
+/// Query parameters of uMap's own embed code
+const UMAP_EMBED_QUERY: &str =
+    "scaleControl=false&miniMap=false&scrollWheelZoom=false&zoomControl=true&editMode=disabled&embedControl=null&captionBar=false";
+
+lazy_static::lazy_static! {
+    /// `#map=zoom/lat/lon` (OpenStreetMap) or `#zoom/lat/lon` (uMap)
+    static ref MAP_FRAGMENT: Regex =
+        Regex::new(r"^(?:map=)?(\d+(?:\.\d+)?)/(-?\d+(?:\.\d+)?)/(-?\d+(?:\.\d+)?)").unwrap();
+    /// `/@lat,lon,zoomz` in Google Maps URLs
+    static ref GOOGLE_VIEW: Regex =
+        Regex::new(r"/@(-?\d+(?:\.\d+)?),(-?\d+(?:\.\d+)?),(\d+(?:\.\d+)?)z").unwrap();
+    /// Longitude (`!2d`) and latitude (`!3d`) in the `pb` parameter of Google
+    /// Maps embed URLs
+    static ref GOOGLE_PB_CENTER: Regex =
+        Regex::new(r"!2d(-?\d+(?:\.\d+)?)!3d(-?\d+(?:\.\d+)?)").unwrap();
+    /// Google Maps short links, which only redirect to the map
+    static ref GOOGLE_SHORT_LINK: Regex =
+        Regex::new(r"^https?://(?:maps\.app\.goo\.gl|goo\.gl/maps)/[\w-]+/?(?:\?\S*)?$").unwrap();
+}
+
+/// Expands short links, such as Google Maps' `maps.app.goo.gl` links, to the
+/// URL they redirect to
+pub trait ShortLinkResolver {
+    /// Returns the `Location` that `url` redirects to
+    fn resolve(&self, url: &str) -> Result<String>;
+}
+
+impl<F> ShortLinkResolver for F
+where
+    F: Fn(&str) -> Result<String>,
+{
+    fn resolve(&self, url: &str) -> Result<String> {
+        self(url)
+    }
+}
+
+/// Serves redirect targets from memory, keyed by short link
+impl ShortLinkResolver for HashMap<String, String> {
+    fn resolve(&self, url: &str) -> Result<String> {
+        self.get(url)
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput(format!("No redirect for {}", url)))
+    }
+}
+
+/// Whether a URL is a map short link that needs expanding before it can be
+/// embedded
+pub(super) fn is_short_link(url: &str) -> bool {
+    GOOGLE_SHORT_LINK.is_match(url.trim())
+}
+
+pub(super) fn map_services() -> Result<Vec<EmbedService>> {
+    Ok(vec![
+        EmbedService::new(
+            "openstreetmap",
+            r"openstreetmap\.org/(?:export/embed\.html\?|.*(?:#map=|[?&]mlat=))",
+            "",
+            600,
+            450,
+        )?
+        .with_hosts(&["openstreetmap.org"])
+        .with_resolver(resolve_openstreetmap),
+        EmbedService::new(
+            "google-maps",
+            r"^(?:https?://)?(?:www\.|maps\.)?google\.[a-z]{2,3}(?:\.[a-z]{2})?/maps\b",
+            "",
+            600,
+            450,
+        )?
+        .with_resolver(resolve_google_maps),
+        EmbedService::new(
+            "umap",
+            r"/(?:[a-z]{2}(?:[-_][a-zA-Z]{2})?/)?map/[\w-]+_\d+",
+            "",
+            600,
+            450,
+        )?
+        .with_hosts(&[
+            "umap.openstreetmap.fr",
+            "umap.openstreetmap.de",
+            "umap.osm.ch",
+        ])
+        .with_resolver(resolve_umap),
+        EmbedService::new(
+            "mapillary",
+            r"mapillary\.com/(?:app/?\?.*\bpKey=|embed\?.*\bimage_key=)",
+            "",
+            640,
+            480,
+        )?
+        .with_hosts(&["mapillary.com"])
+        .with_resolver(resolve_mapillary),
+    ])
+}
+
+/// Share links (`/#map=12/67.9/18.5`, `?mlat=..&mlon=..`) and embed URLs
+/// (`/export/embed.html?bbox=..`)
+fn resolve_openstreetmap(url: &str) -> Option<ResolvedEmbed> {
+    let marker = query_param(url, "marker")
+        .and_then(|marker| parse_lat_lon(&marker))
+        .or_else(|| {
+            let lat = query_param(url, "mlat")?.parse().ok()?;
+            let lon = query_param(url, "mlon")?.parse().ok()?;
+            Some([lat, lon])
+        });
+
+    if let Some(bbox) = query_param(url, "bbox").and_then(|bbox| parse_bbox(&bbox)) {
+        let center = marker.unwrap_or([(bbox[1] + bbox[3]) / 2.0, (bbox[0] + bbox[2]) / 2.0]);
+        let zoom = bbox_zoom(&bbox);
+
+        let mut source = "https://www.openstreetmap.org/".to_string();
+        if let Some([lat, lon]) = marker {
+            source.push_str(&format!("?mlat={}&mlon={}", number(lat), number(lon)));
+        }
+        source.push_str(&format!(
+            "#map={}/{}/{}",
+            number(zoom),
+            number(center[0]),
+            number(center[1])
+        ));
+
+        return Some(ResolvedEmbed {
+            source,
+            embed: url.to_string(),
+            meta: Some(EmbedMeta {
+                center: Some(center),
+                zoom: Some(zoom),
+                bbox: Some(bbox),
+            }),
+        });
+    }
+
+    let (zoom, center) = match url_fragment(url).and_then(|f| MAP_FRAGMENT.captures(f)) {
+        Some(view) => (
+            view[1].parse().ok()?,
+            [view[2].parse().ok()?, view[3].parse().ok()?],
+        ),
+        None => (DEFAULT_ZOOM, marker?),
+    };
+    let bbox = view_bbox(center, zoom);
+    let layer = query_param(url, "layer").unwrap_or_else(|| "mapnik".to_string());
+
+    let mut embed = format!(
+        "https://www.openstreetmap.org/export/embed.html?bbox={}%2C{}%2C{}%2C{}&layer={}",
+        number(bbox[0]),
+        number(bbox[1]),
+        number(bbox[2]),
+        number(bbox[3]),
+        percent_encode(&layer)
+    );
+    if let Some([lat, lon]) = marker {
+        embed.push_str(&format!("&marker={}%2C{}", number(lat), number(lon)));
+    }
+
+    Some(ResolvedEmbed {
+        source: url.to_string(),
+        embed,
+        meta: Some(EmbedMeta {
+            center: Some(center),
+            zoom: Some(zoom),
+            bbox: Some(bbox),
+        }),
+    })
+}
+
+/// Embed URLs (`/maps/embed?pb=..`, `/maps?q=..&output=embed`) and share links
+/// (`/maps/@lat,lon,12z`, `/maps/place/../@lat,lon,12z`, `/maps?q=..`)
+fn resolve_google_maps(url: &str) -> Option<ResolvedEmbed> {
+    if url.contains("/maps/embed") {
+        let pb = query_param(url, "pb")?;
+        let center = GOOGLE_PB_CENTER.captures(&pb).and_then(|cap| {
+            let lon: f64 = cap[1].parse().ok()?;
+            let lat: f64 = cap[2].parse().ok()?;
+            Some([lat, lon])
+        });
+        let source = match center {
+            Some([lat, lon]) => format!(
+                "https://www.google.com/maps/search/?api=1&query={}%2C{}",
+                number(lat),
+                number(lon)
+            ),
+            None => url.to_string(),
+        };
+
+        return Some(ResolvedEmbed {
+            source,
+            embed: url.to_string(),
+            meta: center.map(|center| EmbedMeta {
+                center: Some(center),
+                ..Default::default()
+            }),
+        });
+    }
+
+    if let Some(view) = GOOGLE_VIEW.captures(url) {
+        let lat: f64 = view[1].parse().ok()?;
+        let lon: f64 = view[2].parse().ok()?;
+        let zoom: f64 = view[3].parse().ok()?;
+
+        return Some(ResolvedEmbed {
+            source: url.to_string(),
+            embed: format!(
+                "https://maps.google.com/maps?q={}%2C{}&z={}&output=embed",
+                number(lat),
+                number(lon),
+                number(zoom.round())
+            ),
+            meta: Some(EmbedMeta {
+                center: Some([lat, lon]),
+                zoom: Some(zoom),
+                bbox: None,
+            }),
+        });
+    }
+
+    let query = raw_query_param(url, "q")?;
+    let zoom = query_param(url, "z").and_then(|z| z.parse::<f64>().ok());
+    let meta = EmbedMeta {
+        center: query_param(url, "q").and_then(|q| parse_lat_lon(&q)),
+        zoom,
+        bbox: None,
+    };
+    let zoom_param = zoom.map_or(String::new(), |zoom| format!("&z={}", number(zoom)));
+
+    let is_embed = query_param(url, "output").as_deref() == Some("embed");
+    Some(ResolvedEmbed {
+        source: if is_embed {
+            format!("https://www.google.com/maps?q={}{}", query, zoom_param)
+        } else {
+            url.to_string()
+        },
+        embed: if is_embed {
+            url.to_string()
+        } else {
+            format!(
+                "https://maps.google.com/maps?q={}{}&output=embed",
+                query, zoom_param
+            )
+        },
+        meta: (meta != EmbedMeta::default()).then_some(meta),
+    })
+}
+
+/// Map pages (`/en/map/trails_1234#13/67.9/18.5`), which are embedded with
+/// display options in the query string
+fn resolve_umap(url: &str) -> Option<ResolvedEmbed> {
+    let base = url.split(['?', '#']).next()?;
+    let fragment = url_fragment(url);
+    let fragment_suffix = fragment.map_or(String::new(), |f| format!("#{}", f));
+    let meta = fragment
+        .and_then(|f| MAP_FRAGMENT.captures(f))
+        .and_then(|view| {
+            Some(EmbedMeta {
+                center: Some([view[2].parse().ok()?, view[3].parse().ok()?]),
+                zoom: Some(view[1].parse().ok()?),
+                bbox: None,
+            })
+        });
+
+    let embed = if url.contains('?') {
+        url.to_string()
+    } else {
+        format!("{}?{}{}", base, UMAP_EMBED_QUERY, fragment_suffix)
+    };
+
+    Some(ResolvedEmbed {
+        source: format!("{}{}", base, fragment_suffix),
+        embed,
+        meta,
+    })
+}
+
+/// Viewer links (`/app/?pKey=..&lat=..&lng=..&z=..`) and embed URLs
+/// (`/embed?image_key=..`)
+fn resolve_mapillary(url: &str) -> Option<ResolvedEmbed> {
+    let is_embed = url.contains("/embed");
+    let key = query_param(url, if is_embed { "image_key" } else { "pKey" })?;
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    let center = (|| {
+        let lat = query_param(url, "lat")?.parse().ok()?;
+        let lon = query_param(url, "lng")?.parse().ok()?;
+        Some([lat, lon])
+    })();
+    let zoom = query_param(url, "z").and_then(|z| z.parse().ok());
+    let meta = EmbedMeta {
+        center,
+        zoom,
+        bbox: None,
+    };
+
+    Some(ResolvedEmbed {
+        source: if is_embed {
+            format!("https://www.mapillary.com/app/?pKey={}", key)
+        } else {
+            url.to_string()
+        },
+        embed: if is_embed {
+            url.to_string()
+        } else {
+            format!(
+                "https://www.mapillary.com/embed?image_key={}&style=photo",
+                key
+            )
+        },
+        meta: (meta != EmbedMeta::default()).then_some(meta),
+    })
+}
+
+/// Parses `lat,lon`
+fn parse_lat_lon(value: &str) -> Option<[f64; 2]> {
+    let (lat, lon) = value.split_once(',')?;
+    let lat: f64 = lat.trim().parse().ok()?;
+    let lon: f64 = lon.trim().parse().ok()?;
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some([lat, lon])
+}
+
+/// Parses `min_lon,min_lat,max_lon,max_lat`
+fn parse_bbox(value: &str) -> Option<[f64; 4]> {
+    let parts: Vec<f64> = value
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [min_lon, min_lat, max_lon, max_lat] if min_lon < max_lon && min_lat < max_lat => {
+            Some([min_lon, min_lat, max_lon, max_lat])
+        }
+        _ => None,
+    }
+}
+
+/// Bounding box of the viewport centered on `center` at a Web Mercator zoom level
+fn view_bbox(center: [f64; 2], zoom: f64) -> [f64; 4] {
+    let [lat, lon] = center;
+    let degrees_per_pixel = 360.0 / (256.0 * 2f64.powf(zoom));
+    let half_width = VIEWPORT.0 / 2.0 * degrees_per_pixel;
+    let half_height = VIEWPORT.1 / 2.0 * degrees_per_pixel * lat.to_radians().cos();
+
+    [
+        (lon - half_width).max(-180.0),
+        (lat - half_height).max(-90.0),
+        (lon + half_width).min(180.0),
+        (lat + half_height).min(90.0),
+    ]
+    .map(|value| (value * 1e6).round() / 1e6)
+}
+
+/// Highest zoom level at which the bounding box fits the viewport width
+fn bbox_zoom(bbox: &[f64; 4]) -> f64 {
+    let width = bbox[2] - bbox[0];
+    (360.0 * VIEWPORT.0 / (256.0 * width))
+        .log2()
+        .floor()
+        .clamp(0.0, 19.0)
+}
+
+/// Formats a coordinate or zoom level with at most six decimals
+fn number(value: f64) -> String {
+    let formatted = format!("{:.6}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => "0".to_string(),
+        _ => formatted.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::EmbedRegistry;
+    use crate::models::{EditorJsBlock, LinkToolData, ParagraphData};
+
+    #[test]
+    fn test_openstreetmap_share_link_and_iframe() {
+        let registry = EmbedRegistry::default();
+
+        let embed = registry
+            .detect("https://www.openstreetmap.org/?mlat=67.9&mlon=18.5#map=12/67.9/18.5")
+            .unwrap();
+        assert_eq!(embed.service, "openstreetmap");
+        assert!(embed
+            .embed
+            .starts_with("https://www.openstreetmap.org/export/embed.html?bbox="));
+        assert!(embed.embed.ends_with("&layer=mapnik&marker=67.9%2C18.5"));
+        let meta = embed.meta.unwrap();
+        assert_eq!(meta.center, Some([67.9, 18.5]));
+        assert_eq!(meta.zoom, Some(12.0));
+        let bbox = meta.bbox.unwrap();
+        assert!(bbox[0] < 18.5 && 18.5 < bbox[2] && bbox[1] < 67.9 && 67.9 < bbox[3]);
+
+        let src = "https://www.openstreetmap.org/export/embed.html?bbox=18.0%2C67.7%2C19.0%2C68.1&layer=mapnik&marker=67.9%2C18.5";
+        let embed = registry.detect_iframe(src).unwrap();
+        assert_eq!(embed.embed, src);
+        assert_eq!(
+            embed.source,
+            "https://www.openstreetmap.org/?mlat=67.9&mlon=18.5#map=9/67.9/18.5"
+        );
+        let meta = embed.meta.unwrap();
+        assert_eq!(meta.bbox, Some([18.0, 67.7, 19.0, 68.1]));
+        assert_eq!(meta.zoom, Some(9.0));
+
+        let embed = registry
+            .detect("https://www.openstreetmap.org/?mlat=67.9&mlon=18.5&layer=C%26marker%3D0#map=12/67.9/18.5")
+            .unwrap();
+        assert!(embed
+            .embed
+            .contains("&layer=C%26marker%3D0&marker=67.9%2C18.5"));
+
+        assert!(registry
+            .detect("https://www.openstreetmap.org/about")
+            .is_none());
+    }
+
+    #[test]
+    fn test_google_maps() {
+        let registry = EmbedRegistry::default();
+
+        let embed = registry
+            .detect("https://www.google.com/maps/place/Abisko/@68.3495,18.8312,12z/data=!3m1")
+            .unwrap();
+        assert_eq!(embed.service, "google-maps");
+        assert_eq!(
+            embed.embed,
+            "https://maps.google.com/maps?q=68.3495%2C18.8312&z=12&output=embed"
+        );
+        assert_eq!(embed.meta.unwrap().zoom, Some(12.0));
+
+        let src = "https://www.google.com/maps/embed?pb=!1m18!1m12!1m3!1d3151.8!2d18.8312!3d68.3495!2m3!1f0!2f0!3f0";
+        let embed = registry.detect_iframe(src).unwrap();
+        assert_eq!(embed.embed, src);
+        assert_eq!(
+            embed.source,
+            "https://www.google.com/maps/search/?api=1&query=68.3495%2C18.8312"
+        );
+        assert_eq!(embed.meta.unwrap().center, Some([68.3495, 18.8312]));
+
+        let embed = registry
+            .detect_iframe("https://maps.google.com/maps?q=Abisko&z=10&output=embed")
+            .unwrap();
+        assert_eq!(embed.source, "https://www.google.com/maps?q=Abisko&z=10");
+
+        assert!(registry
+            .detect("https://www.google.com/search?q=maps")
+            .is_none());
+        assert!(registry
+            .detect("https://example.com/?u=https://www.google.com/maps/@68.3,18.8,12z")
+            .is_none());
+    }
+
+    #[test]
+    fn test_umap_and_mapillary() {
+        let registry = EmbedRegistry::default();
+
+        let embed = registry
+            .detect("https://umap.openstreetmap.fr/en/map/kungsleden_12345#10/67.9/18.5")
+            .unwrap();
+        assert_eq!(embed.service, "umap");
+        assert!(embed.embed.starts_with(
+            "https://umap.openstreetmap.fr/en/map/kungsleden_12345?scaleControl=false"
+        ));
+        assert!(embed.embed.ends_with("#10/67.9/18.5"));
+        assert_eq!(embed.meta.unwrap().center, Some([67.9, 18.5]));
+
+        let embed = registry
+            .detect_iframe("https://umap.openstreetmap.fr/en/map/kungsleden_12345?scaleControl=false#10/67.9/18.5")
+            .unwrap();
+        assert_eq!(
+            embed.source,
+            "https://umap.openstreetmap.fr/en/map/kungsleden_12345#10/67.9/18.5"
+        );
+
+        let embed = registry
+            .detect("https://www.mapillary.com/app/?lat=67.9&lng=18.5&z=17&pKey=498763468214164")
+            .unwrap();
+        assert_eq!(embed.service, "mapillary");
+        assert_eq!(
+            embed.embed,
+            "https://www.mapillary.com/embed?image_key=498763468214164&style=photo"
+        );
+        assert_eq!(embed.meta.unwrap().zoom, Some(17.0));
+
+        let embed = registry
+            .detect_iframe("https://www.mapillary.com/embed?image_key=498763468214164&style=photo")
+            .unwrap();
+        assert_eq!(
+            embed.source,
+            "https://www.mapillary.com/app/?pKey=498763468214164"
+        );
+    }
+
+    #[test]
+    fn test_expand_google_maps_short_links() {
+        let registry = EmbedRegistry::default();
+        let redirects = HashMap::from([(
+            "https://maps.app.goo.gl/Ab12Cd34".to_string(),
+            "https://www.google.com/maps/@67.9,18.5,12z?entry=ttu".to_string(),
+        )]);
+        let mut blocks = vec![
+            EditorJsBlock::Paragraph {
+                data: ParagraphData {
+                    text: r#"<a href="https://maps.app.goo.gl/Ab12Cd34">https://maps.app.goo.gl/Ab12Cd34</a>"#.to_string(),
+                },
+            },
+            EditorJsBlock::LinkTool {
+                data: LinkToolData {
+                    link: "https://maps.app.goo.gl/Unknown".to_string(),
+                    meta: Default::default(),
+                },
+            },
+        ];
+
+        assert!(is_short_link("https://goo.gl/maps/Ab12Cd34"));
+        assert!(!is_short_link("https://goo.gl/Ab12Cd34"));
+
+        registry.expand_short_links(&mut blocks, &redirects);
+
+        let EditorJsBlock::Embed { data } = &blocks[0] else {
+            panic!("Expected embed block, got {:?}", blocks[0]);
+        };
+        assert_eq!(data.service, "google-maps");
+        assert_eq!(
+            data.source,
+            "https://www.google.com/maps/@67.9,18.5,12z?entry=ttu"
+        );
+        assert_eq!(data.meta.as_ref().unwrap().center, Some([67.9, 18.5]));
+        assert!(matches!(blocks[1], EditorJsBlock::LinkTool { .. }));
+    }
+}
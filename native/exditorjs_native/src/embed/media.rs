@@ -0,0 +1,53 @@
+//! Self-hosted video and audio files
+
+use crate::models::{EditorJsBlock, MediaData, MediaKind};
+
+/// File extensions of media files, with their kind and MIME type
+const MEDIA_TYPES: &[(&str, MediaKind, &str)] = &[
+    ("mp4", MediaKind::Video, "video/mp4"),
+    ("m4v", MediaKind::Video, "video/mp4"),
+    ("webm", MediaKind::Video, "video/webm"),
+    ("ogv", MediaKind::Video, "video/ogg"),
+    ("mov", MediaKind::Video, "video/quicktime"),
+    ("mp3", MediaKind::Audio, "audio/mpeg"),
+    ("m4a", MediaKind::Audio, "audio/mp4"),
+    ("aac", MediaKind::Audio, "audio/aac"),
+    ("oga", MediaKind::Audio, "audio/ogg"),
+    ("ogg", MediaKind::Audio, "audio/ogg"),
+    ("opus", MediaKind::Audio, "audio/opus"),
+    ("wav", MediaKind::Audio, "audio/wav"),
+    ("flac", MediaKind::Audio, "audio/flac"),
+];
+
+/// Kind and MIME type of a media file URL, from its extension
+pub fn media_type(url: &str) -> Option<(MediaKind, &'static str)> {
+    let path = url.split(['?', '#']).next()?;
+    let file = path.rsplit('/').next()?;
+    let (_, extension) = file.rsplit_once('.')?;
+
+    MEDIA_TYPES
+        .iter()
+        .find(|(ext, _, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|(_, kind, mime_type)| (*kind, *mime_type))
+}
+
+/// Media block with player controls for a link to a media file
+pub(crate) fn media_block(url: &str, caption: Option<String>) -> Option<EditorJsBlock> {
+    let (kind, mime_type) = media_type(url)?;
+
+    Some(EditorJsBlock::Media {
+        data: MediaData {
+            url: url.to_string(),
+            kind,
+            mime_type: Some(mime_type.to_string()),
+            poster: None,
+            controls: Some(true),
+            autoplay: None,
+            looping: None,
+            muted: None,
+            width: None,
+            height: None,
+            caption: caption.filter(|caption| !caption.is_empty()),
+        },
+    })
+}
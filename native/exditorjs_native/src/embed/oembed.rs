@@ -0,0 +1,517 @@
+//! oEmbed lookup and enrichment of embed blocks
+//!
+//! Nothing here opens a network connection: providers are loaded from the
+//! provider list JSON published at <https://oembed.com/providers.json>, and
+//! documents are requested through a caller-supplied [`OEmbedFetcher`], which
+//! may be an HTTP client, a local fixture server or an in-memory map.
+
+use super::{percent_encode, url_host};
+use crate::error::{Error, Result};
+use crate::link_tool::standalone_link;
+use crate::models::{EditorJsBlock, EmbedData};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Size of embeds whose oEmbed response has no dimensions
+const DEFAULT_SIZE: (u32, u32) = (580, 320);
+
+lazy_static::lazy_static! {
+    static ref LINK_TAG: Regex = Regex::new(r"(?is)<link\b([^>]*)>").unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"(?i)\b([a-z-]+)\s*=\s*["']([^"']*)["']"#).unwrap();
+    static ref IFRAME_SRC: Regex =
+        Regex::new(r#"(?is)<iframe\b[^>]*?\bsrc\s*=\s*["']([^"']+)["']"#).unwrap();
+}
+
+/// Loads documents for oEmbed lookups
+pub trait OEmbedFetcher {
+    /// Returns the body of a GET request to `url`
+    fn fetch(&self, url: &str) -> Result<String>;
+}
+
+impl<F> OEmbedFetcher for F
+where
+    F: Fn(&str) -> Result<String>,
+{
+    fn fetch(&self, url: &str) -> Result<String> {
+        self(url)
+    }
+}
+
+/// Serves documents from memory, keyed by URL
+impl OEmbedFetcher for HashMap<String, String> {
+    fn fetch(&self, url: &str) -> Result<String> {
+        self.get(url)
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput(format!("No document for {}", url)))
+    }
+}
+
+/// oEmbed response, see <https://oembed.com/#section2.3>
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct OEmbed {
+    /// `photo`, `video`, `link` or `rich`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: Option<String>,
+    pub author_name: Option<String>,
+    pub author_url: Option<String>,
+    pub provider_name: Option<String>,
+    pub provider_url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    #[serde(deserialize_with = "dimension")]
+    pub thumbnail_width: Option<u32>,
+    #[serde(deserialize_with = "dimension")]
+    pub thumbnail_height: Option<u32>,
+    /// Image URL of `photo` responses
+    pub url: Option<String>,
+    /// Embed HTML of `video` and `rich` responses
+    pub html: Option<String>,
+    #[serde(deserialize_with = "dimension")]
+    pub width: Option<u32>,
+    #[serde(deserialize_with = "dimension")]
+    pub height: Option<u32>,
+}
+
+/// Accepts dimensions given as numbers, numeric strings or `null`
+fn dimension<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u32>, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(n) => n.as_f64().map(|n| n.round() as u32),
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok().map(|n| n.round() as u32),
+        _ => None,
+    })
+}
+
+impl OEmbed {
+    /// Parses an oEmbed JSON response
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// URL to embed: the iframe of the embed HTML, or the image of a photo
+    pub fn embed_url(&self) -> Option<String> {
+        let from_html = self.html.as_deref().and_then(|html| {
+            IFRAME_SRC
+                .captures(html)
+                .map(|caps| caps[1].replace("&amp;", "&"))
+        });
+        from_html.or_else(|| match self.kind.as_str() {
+            "photo" => self.url.clone(),
+            _ => None,
+        })
+    }
+
+    /// Copies the title, thumbnail and author into embed data that lacks them
+    pub fn enrich(&self, data: &mut EmbedData) {
+        fill(&mut data.title, &self.title);
+        fill(&mut data.thumbnail_url, &self.thumbnail_url);
+        fill(&mut data.author_name, &self.author_name);
+        fill(&mut data.author_url, &self.author_url);
+    }
+
+    /// Embed data for `source`, or `None` for responses without anything to
+    /// embed (such as `link` responses)
+    pub fn to_embed_data(&self, source: &str) -> Option<EmbedData> {
+        let embed = self.embed_url()?;
+        let service = self
+            .provider_name
+            .as_deref()
+            .map(service_name)
+            .filter(|name| !name.is_empty())
+            .or_else(|| url_host(source))?;
+
+        let mut data = EmbedData {
+            service,
+            source: source.to_string(),
+            embed,
+            width: self.width.unwrap_or(DEFAULT_SIZE.0),
+            height: self.height.unwrap_or(DEFAULT_SIZE.1),
+            caption: None,
+            meta: None,
+            title: None,
+            thumbnail_url: None,
+            author_name: None,
+            author_url: None,
+            aspect_ratio: None,
+        };
+        self.enrich(&mut data);
+        Some(data)
+    }
+}
+
+fn fill(field: &mut Option<String>, value: &Option<String>) {
+    if field.is_none() {
+        field.clone_from(value);
+    }
+}
+
+/// `"Flickr"` to `"flickr"`, `"Sketchfab 3D"` to `"sketchfab-3d"`
+fn service_name(provider: &str) -> String {
+    provider
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[derive(Deserialize)]
+struct ProviderConfig {
+    provider_name: String,
+    #[serde(default)]
+    provider_url: String,
+    #[serde(default)]
+    endpoints: Vec<EndpointConfig>,
+}
+
+#[derive(Deserialize)]
+struct EndpointConfig {
+    url: String,
+    #[serde(default)]
+    schemes: Vec<String>,
+}
+
+/// An oEmbed endpoint and the URL schemes it serves
+#[derive(Debug, Clone)]
+pub struct OEmbedProvider {
+    pub name: String,
+    /// Endpoint URL, with `{format}` already replaced by `json`
+    pub endpoint: String,
+    schemes: Vec<Regex>,
+    host: Option<String>,
+}
+
+impl OEmbedProvider {
+    /// Whether the provider serves `url`. Providers without schemes serve the
+    /// URLs on the host of their provider URL.
+    pub fn matches(&self, url: &str) -> bool {
+        if self.schemes.is_empty() {
+            let host = url_host(url);
+            let host = host
+                .as_deref()
+                .map(|host| host.strip_prefix("www.").unwrap_or(host));
+            return host.is_some() && host == self.host.as_deref();
+        }
+        self.schemes.iter().any(|scheme| scheme.is_match(url))
+    }
+
+    /// oEmbed request URL for `url`
+    pub fn request_url(&self, url: &str) -> String {
+        let separator = if self.endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        format!(
+            "{}{}url={}&format=json",
+            self.endpoint,
+            separator,
+            percent_encode(url)
+        )
+    }
+}
+
+/// Turns a scheme such as `https://*.flickr.com/photos/*` into a regex that
+/// accepts both `http` and `https`
+fn compile_scheme(scheme: &str) -> Result<Regex> {
+    let rest = scheme
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let pattern = rest
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+
+    Regex::new(&format!("^https?://{}$", pattern))
+        .map_err(|e| Error::InvalidInput(format!("Invalid oEmbed scheme '{}': {}", scheme, e)))
+}
+
+/// oEmbed providers, in the order of the provider list
+#[derive(Debug, Clone, Default)]
+pub struct OEmbedProviders {
+    providers: Vec<OEmbedProvider>,
+}
+
+impl OEmbedProviders {
+    /// Parses a provider list in the format of <https://oembed.com/providers.json>
+    pub fn from_json(json: &str) -> Result<Self> {
+        let configs: Vec<ProviderConfig> = serde_json::from_str(json)?;
+        let mut providers = Vec::new();
+
+        for config in configs {
+            let host = url_host(&config.provider_url)
+                .map(|host| host.strip_prefix("www.").unwrap_or(&host).to_string());
+            for endpoint in config.endpoints {
+                providers.push(OEmbedProvider {
+                    name: config.provider_name.clone(),
+                    endpoint: endpoint.url.replace("{format}", "json"),
+                    schemes: endpoint
+                        .schemes
+                        .iter()
+                        .map(|scheme| compile_scheme(scheme))
+                        .collect::<Result<_>>()?,
+                    host: host.clone(),
+                });
+            }
+        }
+
+        Ok(OEmbedProviders { providers })
+    }
+
+    pub fn providers(&self) -> &[OEmbedProvider] {
+        &self.providers
+    }
+
+    /// First provider serving `url`
+    pub fn find(&self, url: &str) -> Option<&OEmbedProvider> {
+        self.providers.iter().find(|provider| provider.matches(url))
+    }
+}
+
+/// Finds the JSON oEmbed endpoint advertised by a page with
+/// `<link rel="alternate" type="application/json+oembed" href="...">`
+pub fn discover_endpoint(html: &str) -> Option<String> {
+    LINK_TAG.captures_iter(html).find_map(|caps| {
+        let attr = |name: &str| {
+            ATTRIBUTE
+                .captures_iter(&caps[1])
+                .find(|attr| attr[1].eq_ignore_ascii_case(name))
+                .map(|attr| attr[2].to_string())
+        };
+        attr("type")
+            .filter(|kind| kind.eq_ignore_ascii_case("application/json+oembed"))
+            .and(attr("href"))
+            .map(|href| href.replace("&amp;", "&"))
+    })
+}
+
+/// Looks up oEmbed data through a fetcher
+pub struct OEmbedClient<F> {
+    providers: OEmbedProviders,
+    fetcher: F,
+    discovery: bool,
+}
+
+impl<F: OEmbedFetcher> OEmbedClient<F> {
+    pub fn new(providers: OEmbedProviders, fetcher: F) -> Self {
+        OEmbedClient {
+            providers,
+            fetcher,
+            discovery: false,
+        }
+    }
+
+    /// Also fetches pages of URLs no provider serves, looking for an advertised
+    /// oEmbed endpoint
+    pub fn with_discovery(mut self) -> Self {
+        self.discovery = true;
+        self
+    }
+
+    /// oEmbed data for `url`, or `None` when no provider serves it
+    pub fn lookup(&self, url: &str) -> Result<Option<OEmbed>> {
+        let request = match self.providers.find(url) {
+            Some(provider) => provider.request_url(url),
+            None if self.discovery => match discover_endpoint(&self.fetcher.fetch(url)?) {
+                Some(endpoint) => endpoint,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        OEmbed::from_json(&self.fetcher.fetch(&request)?).map(Some)
+    }
+
+    /// Embed data for a URL with something to embed
+    pub fn resolve(&self, url: &str) -> Result<Option<EmbedData>> {
+        Ok(self
+            .lookup(url)?
+            .and_then(|oembed| oembed.to_embed_data(url)))
+    }
+
+    /// Adds oEmbed titles, thumbnails and authors to embed blocks, and turns
+    /// link previews and paragraphs holding nothing but a link into embeds
+    /// when the link has something to embed. Lookups that fail leave their
+    /// block unchanged.
+    pub fn enrich_blocks(&self, blocks: &mut [EditorJsBlock]) {
+        for block in blocks {
+            match block {
+                EditorJsBlock::Embed { data } => {
+                    if let Ok(Some(oembed)) = self.lookup(&data.source) {
+                        oembed.enrich(data);
+                    }
+                }
+                EditorJsBlock::Paragraph { data } => {
+                    let Some(url) = standalone_link(&data.text) else {
+                        continue;
+                    };
+                    if let Ok(Some(data)) = self.resolve(&url) {
+                        *block = EditorJsBlock::Embed { data };
+                    }
+                }
+                EditorJsBlock::LinkTool { data } => {
+                    if let Ok(Some(data)) = self.resolve(&data.link) {
+                        *block = EditorJsBlock::Embed { data };
+                    }
+                }
+                EditorJsBlock::Toggle { children, .. } => self.enrich_blocks(children),
+                EditorJsBlock::Tuned { block, .. } => {
+                    self.enrich_blocks(std::slice::from_mut(block.as_mut()))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ParagraphData;
+
+    const PROVIDERS: &str = r#"[
+        {
+            "provider_name": "Flickr",
+            "provider_url": "https://www.flickr.com/",
+            "endpoints": [{
+                "schemes": ["http://*.flickr.com/photos/*", "http://flic.kr/p/*"],
+                "url": "https://www.flickr.com/services/oembed/",
+                "discovery": true
+            }]
+        },
+        {
+            "provider_name": "Sketchfab",
+            "provider_url": "http://sketchfab.com",
+            "endpoints": [{ "url": "http://sketchfab.com/oembed.{format}" }]
+        }
+    ]"#;
+
+    fn fixtures() -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "https://www.flickr.com/services/oembed/?url=https%3A%2F%2Fwww.flickr.com%2Fphotos%2Fhiker%2F123%2F&format=json".to_string(),
+                r#"{"type": "photo", "version": "1.0", "title": "Summit", "author_name": "hiker",
+                    "author_url": "https://www.flickr.com/photos/hiker/", "provider_name": "Flickr",
+                    "url": "https://live.staticflickr.com/123_b.jpg", "width": "1024", "height": 683,
+                    "thumbnail_url": "https://live.staticflickr.com/123_q.jpg"}"#
+                    .to_string(),
+            ),
+            (
+                "http://sketchfab.com/oembed.json?url=https%3A%2F%2Fsketchfab.com%2F3d-models%2Fpeak-abc&format=json".to_string(),
+                r#"{"type": "rich", "title": "Peak", "provider_name": "Sketchfab", "width": 640, "height": 480,
+                    "html": "<iframe width=\"640\" height=\"480\" src=\"https://sketchfab.com/models/abc/embed?a=1&amp;b=2\"></iframe>"}"#
+                    .to_string(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_providers_match_schemes_and_hosts() {
+        let providers = OEmbedProviders::from_json(PROVIDERS).unwrap();
+        assert_eq!(providers.providers().len(), 2);
+        assert_eq!(
+            providers
+                .find("https://www.flickr.com/photos/hiker/123/")
+                .map(|p| p.name.as_str()),
+            Some("Flickr")
+        );
+        assert_eq!(
+            providers
+                .find("https://sketchfab.com/3d-models/peak-abc")
+                .map(|p| p.endpoint.as_str()),
+            Some("http://sketchfab.com/oembed.json")
+        );
+        assert!(providers.find("https://www.flickr.com/about").is_none());
+        assert!(providers
+            .find("https://example.com/sketchfab.com")
+            .is_none());
+        assert!(OEmbedProviders::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_resolve_with_in_memory_fetcher() {
+        let client = OEmbedClient::new(OEmbedProviders::from_json(PROVIDERS).unwrap(), fixtures());
+
+        let data = client
+            .resolve("https://www.flickr.com/photos/hiker/123/")
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.service, "flickr");
+        assert_eq!(data.embed, "https://live.staticflickr.com/123_b.jpg");
+        assert_eq!((data.width, data.height), (1024, 683));
+        assert_eq!(data.title.as_deref(), Some("Summit"));
+        assert_eq!(data.author_name.as_deref(), Some("hiker"));
+        assert_eq!(
+            data.thumbnail_url.as_deref(),
+            Some("https://live.staticflickr.com/123_q.jpg")
+        );
+
+        let data = client
+            .resolve("https://sketchfab.com/3d-models/peak-abc")
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.embed, "https://sketchfab.com/models/abc/embed?a=1&b=2");
+
+        assert!(client.resolve("https://example.com/").unwrap().is_none());
+        assert!(client
+            .resolve("https://www.flickr.com/photos/hiker/404/")
+            .is_err());
+    }
+
+    #[test]
+    fn test_discovery_and_enrich_blocks() {
+        let fetcher = |url: &str| -> Result<String> {
+            match url {
+                "https://trails.example.com/t/1" => Ok(r#"<html><head>
+                    <link rel="alternate" type="application/json+oembed"
+                          href="https://trails.example.com/oembed?url=t%2F1&amp;format=json">
+                    </head></html>"#
+                    .to_string()),
+                "https://trails.example.com/oembed?url=t%2F1&format=json" => Ok(
+                    r#"{"type": "rich", "title": "Kungsleden", "provider_name": "Trails",
+                        "html": "<iframe src=\"https://trails.example.com/embed/1\"></iframe>"}"#
+                        .to_string(),
+                ),
+                _ => Err(Error::InvalidInput(url.to_string())),
+            }
+        };
+        let client = OEmbedClient::new(OEmbedProviders::default(), fetcher).with_discovery();
+
+        let mut blocks = vec![
+            EditorJsBlock::Paragraph {
+                data: ParagraphData {
+                    text: r#"<a href="https://trails.example.com/t/1">Kungsleden</a>"#.to_string(),
+                },
+            },
+            EditorJsBlock::Paragraph {
+                data: ParagraphData {
+                    text: "See https://trails.example.com/t/1".to_string(),
+                },
+            },
+            EditorJsBlock::Paragraph {
+                data: ParagraphData {
+                    text: "https://unknown.example.com/".to_string(),
+                },
+            },
+        ];
+        client.enrich_blocks(&mut blocks);
+
+        match &blocks[0] {
+            EditorJsBlock::Embed { data } => {
+                assert_eq!(data.service, "trails");
+                assert_eq!(data.embed, "https://trails.example.com/embed/1");
+                assert_eq!((data.width, data.height), DEFAULT_SIZE);
+                assert_eq!(data.title.as_deref(), Some("Kungsleden"));
+            }
+            other => panic!("Expected embed block, got {:?}", other),
+        }
+        assert!(matches!(blocks[1], EditorJsBlock::Paragraph { .. }));
+        assert!(matches!(blocks[2], EditorJsBlock::Paragraph { .. }));
+    }
+}
@@ -0,0 +1,266 @@
+//! Social media posts: Facebook and TikTok services, and the official embed
+//! snippets of Twitter, Instagram, TikTok and Facebook
+
+use super::{html_attributes, percent_encode, query_param, EmbedService, ResolvedEmbed};
+use crate::error::Result;
+use crate::html::find_matching_close;
+use regex::Regex;
+
+const FACEBOOK_POST_PATTERN: &str =
+    r"facebook\.com/(?:[\w.-]+/posts/[\w.-]+|(?:permalink|story)\.php\?|plugins/post\.php\?)";
+
+const FACEBOOK_VIDEO_PATTERN: &str = r"facebook\.com/(?:[\w.-]+/videos/(?:[\w.-]+/)?\d+|watch/?\?(?:[^#]*&)?v=\d+|plugins/video\.php\?)";
+
+lazy_static::lazy_static! {
+    static ref FACEBOOK_VIDEO: Regex = Regex::new(FACEBOOK_VIDEO_PATTERN).unwrap();
+    static ref SNIPPET_OPEN: Regex = Regex::new(r"(?i)^\s*<(blockquote|div)\b([^>]*)>").unwrap();
+    /// Scripts loading the embed, and the `fb-root` element of the Facebook SDK
+    static ref SNIPPET_SCRIPT: Regex = Regex::new(
+        r#"(?is)^\s*(?:<script\b[^>]*>.*?</script>|<div\s+id=["']fb-root["']\s*>\s*</div>)"#
+    )
+    .unwrap();
+    static ref PARAGRAPH: Regex = Regex::new(r"(?is)<p\b[^>]*>(.*?)</p>").unwrap();
+    static ref SECTION: Regex = Regex::new(r"(?is)<section\b[^>]*>(.*)</section>").unwrap();
+    static ref TWEET_LINK: Regex = Regex::new(
+        r#"(?i)href=["'](https?://(?:www\.|mobile\.)?(?:twitter|x)\.com/\w+/status/\d+)"#
+    )
+    .unwrap();
+    static ref INSTAGRAM_LINK: Regex =
+        Regex::new(r#"(?i)href=["'](https?://(?:www\.)?instagram\.com/(?:p|reel|tv)/[\w-]+)"#)
+            .unwrap();
+    /// `pic.twitter.com/...` links to the media of a tweet
+    static ref TWEET_MEDIA_LINK: Regex =
+        Regex::new(r"(?is)<a\b[^>]*>\s*pic\.(?:twitter|x)\.com/[^<]*</a>").unwrap();
+    /// Author (`@user`) and sound (`♬ ...`) links of a TikTok snippet
+    static ref TIKTOK_CREDIT_LINK: Regex =
+        Regex::new(r"(?is)<a\b[^>]*>\s*(?:@|♬)[^<]*</a>").unwrap();
+    static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+pub(super) fn social_services() -> Result<Vec<EmbedService>> {
+    Ok(vec![
+        EmbedService::new("facebook", FACEBOOK_POST_PATTERN, "", 500, 600)?
+            .with_hosts(&["facebook.com", "m.facebook.com"])
+            .with_resolver(resolve_facebook),
+        EmbedService::new("facebook-video", FACEBOOK_VIDEO_PATTERN, "", 560, 315)?
+            .with_hosts(&["facebook.com", "m.facebook.com"])
+            .with_resolver(resolve_facebook),
+        EmbedService::new(
+            "tiktok",
+            r"tiktok\.com/@[\w.-]+/video/(\d+)",
+            "https://www.tiktok.com/embed/v2/{}",
+            325,
+            575,
+        )?
+        .with_hosts(&["tiktok.com", "m.tiktok.com"]),
+    ])
+}
+
+/// Facebook embeds the post or video given in the `href` of its plugin URLs
+fn resolve_facebook(url: &str) -> Option<ResolvedEmbed> {
+    if url.contains("/plugins/") {
+        let href = query_param(url, "href")?;
+        return Some(ResolvedEmbed::new(href, url.to_string()));
+    }
+
+    let plugin = if FACEBOOK_VIDEO.is_match(url) {
+        "video"
+    } else {
+        "post"
+    };
+    let source = url.split('#').next()?;
+    let embed = format!(
+        "https://www.facebook.com/plugins/{}.php?href={}&show_text=true",
+        plugin,
+        percent_encode(source)
+    );
+    Some(ResolvedEmbed::new(url.to_string(), embed))
+}
+
+/// Post URL and caption of an official embed snippet
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EmbedSnippet {
+    pub url: String,
+    pub caption: Option<String>,
+}
+
+/// Recognizes the `<blockquote>` of a Twitter, Instagram, TikTok or Facebook
+/// embed snippet, or the `<div class="fb-post">` wrapping a Facebook one. The
+/// caption is the quoted post text.
+pub(crate) fn parse_snippet(tag: &str, attrs: &str, content: &str) -> Option<EmbedSnippet> {
+    let attributes = html_attributes(attrs);
+    let attr = |name: &str| {
+        attributes
+            .iter()
+            .find(|(attr, value)| attr == name && !value.trim().is_empty())
+            .map(|(_, value)| value.trim().replace("&amp;", "&"))
+    };
+    let class = attr("class").unwrap_or_default();
+    let has_class = |name: &str| class.split_whitespace().any(|class| class == name);
+    let first_paragraph = || {
+        PARAGRAPH
+            .captures(content)
+            .and_then(|p| text_content(&p[1]))
+    };
+
+    let (url, caption) = if !tag.eq_ignore_ascii_case("blockquote") {
+        if !tag.eq_ignore_ascii_case("div") || !(has_class("fb-post") || has_class("fb-video")) {
+            return None;
+        }
+        (attr("data-href")?, first_paragraph())
+    } else if has_class("twitter-tweet") || has_class("twitter-video") {
+        let url = TWEET_LINK.captures_iter(content).last()?[1].to_string();
+        let caption = PARAGRAPH
+            .captures(content)
+            .and_then(|p| text_content(&TWEET_MEDIA_LINK.replace_all(&p[1], "")));
+        (url, caption)
+    } else if has_class("instagram-media") {
+        let url = attr("data-instgrm-permalink").or_else(|| {
+            INSTAGRAM_LINK
+                .captures(content)
+                .map(|link| link[1].to_string())
+        })?;
+        (strip_query(&url), first_paragraph())
+    } else if has_class("tiktok-embed") {
+        let caption = SECTION
+            .captures(content)
+            .and_then(|section| text_content(&TIKTOK_CREDIT_LINK.replace_all(&section[1], "")));
+        (strip_query(&attr("cite")?), caption)
+    } else if has_class("fb-xfbml-parse-ignore") {
+        (attr("cite")?, first_paragraph())
+    } else {
+        return None;
+    };
+
+    Some(EmbedSnippet { url, caption })
+}
+
+/// Parses an embed snippet at the start of `html`, returning it with the
+/// length of the snippet, including the `<script>` tags loading the embed
+/// around it
+pub(crate) fn parse_snippet_html(html: &str) -> Option<(EmbedSnippet, usize)> {
+    let mut start = 0;
+    while let Some(script) = SNIPPET_SCRIPT.find(&html[start..]) {
+        start += script.end();
+    }
+
+    let open = SNIPPET_OPEN.captures(&html[start..])?;
+    let tag = open[1].to_lowercase();
+    let content_start = start + open.get(0)?.end();
+    let (close_start, close_end) = find_matching_close(&html[content_start..], 0, &tag)?;
+    let snippet = parse_snippet(
+        &tag,
+        &open[2],
+        &html[content_start..content_start + close_start],
+    )?;
+
+    let mut end = content_start + close_end;
+    while let Some(script) = SNIPPET_SCRIPT.find(&html[end..]) {
+        end += script.end();
+    }
+    Some((snippet, end))
+}
+
+/// Text of an HTML fragment with whitespace collapsed
+fn text_content(html: &str) -> Option<String> {
+    let text = TAG.replace_all(html, " ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn strip_query(url: &str) -> String {
+    url.split(['?', '#']).next().unwrap_or(url).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::EmbedRegistry;
+
+    const TWEET: &str = r#"<blockquote class="twitter-tweet"><p lang="en" dir="ltr">Sunrise over <b>Kebnekaise</b> <a href="https://t.co/abc">pic.twitter.com/abc</a></p>&mdash; Trail Runner (@trailrunner) <a href="https://twitter.com/trailrunner/status/1234567890123?ref_src=twsrc%5Etfw">June 1, 2024</a></blockquote> <script async src="https://platform.twitter.com/widgets.js" charset="utf-8"></script>
+After"#;
+
+    #[test]
+    fn test_snippets() {
+        let (snippet, len) = parse_snippet_html(TWEET).unwrap();
+        assert_eq!(
+            snippet,
+            EmbedSnippet {
+                url: "https://twitter.com/trailrunner/status/1234567890123".to_string(),
+                caption: Some("Sunrise over Kebnekaise".to_string()),
+            }
+        );
+        assert_eq!(&TWEET[len..], "\nAfter");
+
+        let instagram = r#"<blockquote class="instagram-media" data-instgrm-captioned data-instgrm-permalink="https://www.instagram.com/p/C8abc_12/?utm_source=ig_embed&amp;utm_campaign=loading" data-instgrm-version="14"><div><a href="https://www.instagram.com/p/C8abc_12/?utm_source=ig_embed">View this post on Instagram</a><p style="margin:8px 0"><a href="https://www.instagram.com/p/C8abc_12/">Camp at Alesjaure</a></p></div></blockquote>"#;
+        let (snippet, _) = parse_snippet_html(instagram).unwrap();
+        assert_eq!(snippet.url, "https://www.instagram.com/p/C8abc_12/");
+        assert_eq!(snippet.caption.as_deref(), Some("Camp at Alesjaure"));
+
+        let tiktok = r#"<blockquote class="tiktok-embed" cite="https://www.tiktok.com/@hiker/video/7301234567890" data-video-id="7301234567890"><section><a target="_blank" title="@hiker" href="https://www.tiktok.com/@hiker">@hiker</a> Crossing the pass <a title="hiking" href="https://www.tiktok.com/tag/hiking">#hiking</a> <a href="https://www.tiktok.com/music/x">♬ original sound - hiker</a></section></blockquote> <script async src="https://www.tiktok.com/embed.js"></script>"#;
+        let (snippet, len) = parse_snippet_html(tiktok).unwrap();
+        assert_eq!(
+            snippet.url,
+            "https://www.tiktok.com/@hiker/video/7301234567890"
+        );
+        assert_eq!(
+            snippet.caption.as_deref(),
+            Some("Crossing the pass #hiking")
+        );
+        assert_eq!(len, tiktok.len());
+
+        let facebook = r#"<div id="fb-root"></div><script async defer crossorigin="anonymous" src="https://connect.facebook.net/en_US/sdk.js#xfbml=1&version=v19.0"></script><div class="fb-post" data-href="https://www.facebook.com/trailclub/posts/pfbid0abc" data-width="500"><blockquote cite="https://www.facebook.com/trailclub/posts/pfbid0abc" class="fb-xfbml-parse-ignore"><p>Trail report: snow on the pass.</p>Posted by <a href="https://www.facebook.com/trailclub">Trail Club</a></blockquote></div>"#;
+        let (snippet, len) = parse_snippet_html(facebook).unwrap();
+        assert_eq!(
+            snippet.url,
+            "https://www.facebook.com/trailclub/posts/pfbid0abc"
+        );
+        assert_eq!(
+            snippet.caption.as_deref(),
+            Some("Trail report: snow on the pass.")
+        );
+        assert_eq!(len, facebook.len());
+
+        assert!(parse_snippet_html("<blockquote><p>Quote</p></blockquote>").is_none());
+        assert!(parse_snippet_html(r#"<div class="post"><p>Text</p></div>"#).is_none());
+    }
+
+    #[test]
+    fn test_facebook_and_tiktok_services() {
+        let registry = EmbedRegistry::default();
+
+        let embed = registry
+            .detect("https://www.facebook.com/trailclub/posts/pfbid0abc")
+            .unwrap();
+        assert_eq!(embed.service, "facebook");
+        assert_eq!(
+            embed.embed,
+            "https://www.facebook.com/plugins/post.php?href=https%3A%2F%2Fwww.facebook.com%2Ftrailclub%2Fposts%2Fpfbid0abc&show_text=true"
+        );
+
+        let embed = registry
+            .detect("https://www.facebook.com/watch/?v=1234567890")
+            .unwrap();
+        assert_eq!(
+            (embed.service.as_str(), embed.height),
+            ("facebook-video", 315)
+        );
+
+        let embed = registry
+            .detect_iframe("https://www.facebook.com/plugins/video.php?href=https%3A%2F%2Fwww.facebook.com%2Ftrailclub%2Fvideos%2F987654321%2F&width=560")
+            .unwrap();
+        assert_eq!(embed.service, "facebook-video");
+        assert_eq!(
+            embed.source,
+            "https://www.facebook.com/trailclub/videos/987654321/"
+        );
+
+        let embed = registry
+            .detect("https://www.tiktok.com/@hiker/video/7301234567890?lang=en")
+            .unwrap();
+        assert_eq!(embed.embed, "https://www.tiktok.com/embed/v2/7301234567890");
+        assert!(registry
+            .detect("https://www.facebook.com/trailclub")
+            .is_none());
+    }
+}
@@ -0,0 +1,249 @@
+//! URL parameters of video embeds: YouTube start times, playlists and Shorts,
+//! and Vimeo private links
+
+use super::{query_param, url_fragment, url_host, ResolvedEmbed};
+use regex::Regex;
+
+/// YouTube watch, Shorts, live and playlist page URLs
+pub(super) const YOUTUBE_PATTERN: &str = r"(?:youtube\.com/(?:watch\?(?:[^#]*&)?v=|shorts/|live/|v/)|youtu\.be/)([a-zA-Z0-9_-]{11})|youtube\.com/playlist\?(?:[^#]*&)?list=([\w-]+)";
+
+/// YouTube player URLs, including the privacy-enhanced youtube-nocookie.com
+pub(super) const YOUTUBE_EMBED_PATTERN: &str =
+    r"(?:youtube|youtube-nocookie)\.com/embed/([a-zA-Z0-9_-]{11}|videoseries)";
+
+/// Vimeo video pages, optionally with the hash of a private (unlisted) video
+pub(super) const VIMEO_PATTERN: &str =
+    r"vimeo\.com/(?:channels/[\w-]+/|groups/[\w-]+/videos/)?(\d+)(?:/([a-f0-9]{6,}))?";
+
+pub(super) const VIMEO_EMBED_PATTERN: &str = r"player\.vimeo\.com/video/(\d+)";
+
+lazy_static::lazy_static! {
+    static ref YOUTUBE_PAGE: Regex = Regex::new(YOUTUBE_PATTERN).unwrap();
+    static ref YOUTUBE_EMBED: Regex = Regex::new(YOUTUBE_EMBED_PATTERN).unwrap();
+    static ref VIMEO_PAGE: Regex = Regex::new(VIMEO_PATTERN).unwrap();
+    static ref VIMEO_EMBED: Regex = Regex::new(VIMEO_EMBED_PATTERN).unwrap();
+    /// `90`, `90s`, `1m30s` or `1h2m3s`
+    static ref DURATION: Regex =
+        Regex::new(r"^(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s?)?$").unwrap();
+}
+
+/// Carries the start (`t`, `start`) and end times and the playlist of a page
+/// URL into the embed URL, and back into the page URL of an embed
+pub(super) fn resolve_youtube(url: &str) -> Option<ResolvedEmbed> {
+    let start = query_param(url, "start")
+        .or_else(|| query_param(url, "t"))
+        .or_else(|| fragment_param(url, "t"))
+        .and_then(|time| parse_seconds(&time));
+    let end = query_param(url, "end").and_then(|time| parse_seconds(&time));
+    let list = query_param(url, "list").filter(|list| is_token(list));
+
+    if let Some(caps) = YOUTUBE_EMBED.captures(url) {
+        let mut source = match &caps[1] {
+            "videoseries" => format!("https://www.youtube.com/playlist?list={}", list?),
+            id => {
+                let mut source = format!("https://www.youtube.com/watch?v={}", id);
+                if let Some(list) = &list {
+                    source.push_str(&format!("&list={}", list));
+                }
+                source
+            }
+        };
+        if let Some(start) = start.filter(|_| !source.contains("/playlist")) {
+            source.push_str(&format!("&t={}s", start));
+        }
+        return Some(ResolvedEmbed::new(source, url.to_string()));
+    }
+
+    let caps = YOUTUBE_PAGE.captures(url)?;
+    let (video, list) = match caps.get(1) {
+        Some(id) => (id.as_str(), list),
+        None => ("videoseries", Some(caps[2].to_string())),
+    };
+
+    let mut params = Vec::new();
+    if let Some(list) = list {
+        params.push(format!("list={}", list));
+    }
+    if let Some(start) = start {
+        params.push(format!("start={}", start));
+    }
+    if let Some(end) = end {
+        params.push(format!("end={}", end));
+    }
+
+    let mut embed = format!("https://www.youtube.com/embed/{}", video);
+    if !params.is_empty() {
+        embed.push('?');
+        embed.push_str(&params.join("&"));
+    }
+    Some(ResolvedEmbed::new(url.to_string(), embed))
+}
+
+/// Carries the hash of private videos (`/ID/HASH` or `?h=HASH`) and the start
+/// time (`#t=90s`) between page and player URLs
+pub(super) fn resolve_vimeo(url: &str) -> Option<ResolvedEmbed> {
+    let time = fragment_param(url, "t")
+        .and_then(|time| parse_seconds(&time))
+        .map_or(String::new(), |start| format!("#t={}s", start));
+    let hash_param = || query_param(url, "h").filter(|hash| is_hash(hash));
+
+    let is_player = url_host(url).is_some_and(|host| host == "player.vimeo.com");
+    if let Some(caps) = VIMEO_EMBED.captures(url).filter(|_| is_player) {
+        let mut source = format!("https://vimeo.com/{}", &caps[1]);
+        if let Some(hash) = hash_param() {
+            source.push_str(&format!("/{}", hash));
+        }
+        source.push_str(&time);
+        return Some(ResolvedEmbed::new(source, url.to_string()));
+    }
+
+    let caps = VIMEO_PAGE.captures(url)?;
+    let hash = caps
+        .get(2)
+        .map(|hash| hash.as_str().to_string())
+        .or_else(hash_param);
+
+    let mut embed = format!("https://player.vimeo.com/video/{}", &caps[1]);
+    if let Some(hash) = hash {
+        embed.push_str(&format!("?h={}", hash));
+    }
+    embed.push_str(&time);
+    Some(ResolvedEmbed::new(url.to_string(), embed))
+}
+
+/// Value of a `name=value` pair in the URL fragment, such as `#t=90`
+fn fragment_param(url: &str, name: &str) -> Option<String> {
+    url_fragment(url)?
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => Some(value.to_string()),
+            _ => None,
+        })
+}
+
+/// Parses a time offset in seconds, returning `None` for zero or invalid times
+fn parse_seconds(time: &str) -> Option<u32> {
+    let caps = DURATION.captures(time.trim())?;
+    let part = |i: usize| -> Option<u32> {
+        caps.get(i)
+            .map_or(Some(0), |value| value.as_str().parse().ok())
+    };
+    let seconds = part(1)?
+        .checked_mul(3600)?
+        .checked_add(part(2)?.checked_mul(60)?)?
+        .checked_add(part(3)?)?;
+
+    (seconds > 0).then_some(seconds)
+}
+
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn is_hash(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::embed::EmbedRegistry;
+
+    #[test]
+    fn test_youtube_url_forms() {
+        let registry = EmbedRegistry::default();
+        let cases = [
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=90s",
+                "https://www.youtube.com/embed/dQw4w9WgXcQ?start=90",
+            ),
+            (
+                "https://youtu.be/dQw4w9WgXcQ?t=1m30s",
+                "https://www.youtube.com/embed/dQw4w9WgXcQ?start=90",
+            ),
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ#t=1h2m3s",
+                "https://www.youtube.com/embed/dQw4w9WgXcQ?start=3723",
+            ),
+            (
+                "https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG",
+                "https://www.youtube.com/embed/dQw4w9WgXcQ?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG",
+            ),
+            (
+                "https://www.youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG",
+                "https://www.youtube.com/embed/videoseries?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG",
+            ),
+            (
+                "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+                "https://www.youtube.com/embed/dQw4w9WgXcQ",
+            ),
+            (
+                "https://m.youtube.com/live/dQw4w9WgXcQ?t=0",
+                "https://www.youtube.com/embed/dQw4w9WgXcQ",
+            ),
+        ];
+
+        for (url, embed) in cases {
+            let detected = registry.detect(url).unwrap();
+            assert_eq!(detected.service, "youtube", "{}", url);
+            assert_eq!(detected.source, url);
+            assert_eq!(detected.embed, embed, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_youtube_embed_urls() {
+        let registry = EmbedRegistry::default();
+        let cases = [
+            (
+                "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?start=90&list=PL123",
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123&t=90s",
+            ),
+            (
+                "https://www.youtube.com/embed/videoseries?list=PL123",
+                "https://www.youtube.com/playlist?list=PL123",
+            ),
+        ];
+
+        for (src, source) in cases {
+            let detected = registry.detect_iframe(src).unwrap();
+            assert_eq!(detected.source, source);
+            assert_eq!(detected.embed, src);
+        }
+    }
+
+    #[test]
+    fn test_vimeo_url_forms() {
+        let registry = EmbedRegistry::default();
+        let cases = [
+            (
+                "https://vimeo.com/123456789/abcdef1234",
+                "https://player.vimeo.com/video/123456789?h=abcdef1234",
+            ),
+            (
+                "https://vimeo.com/123456789?h=abcdef1234#t=1m30s",
+                "https://player.vimeo.com/video/123456789?h=abcdef1234#t=90s",
+            ),
+            (
+                "https://vimeo.com/channels/staffpicks/123456789",
+                "https://player.vimeo.com/video/123456789",
+            ),
+        ];
+
+        for (url, embed) in cases {
+            let detected = registry.detect(url).unwrap();
+            assert_eq!(detected.service, "vimeo", "{}", url);
+            assert_eq!(detected.embed, embed, "{}", url);
+        }
+
+        let src = "https://player.vimeo.com/video/123456789?h=abcdef1234&badge=0#t=45s";
+        let detected = registry.detect_iframe(src).unwrap();
+        assert_eq!(
+            detected.source,
+            "https://vimeo.com/123456789/abcdef1234#t=45s"
+        );
+        assert_eq!(detected.embed, src);
+    }
+}
@@ -0,0 +1,176 @@
+//! Footnote numbering and output, shared by the HTML and Markdown parsers
+//!
+//! Parsers mark footnote references as `<sup><a href="#key">…</a></sup>` (or
+//! `<a href="#key"><sup>…</sup></a>`, as written by Pandoc) and
+//! collect the footnote definitions by key. [`apply_footnotes`] then numbers the
+//! footnotes in order of first reference and emits them according to
+//! [`ConversionOptions::footnotes`].
+
+use crate::models::*;
+use crate::options::{ConversionOptions, FootnoteStyle};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+lazy_static::lazy_static! {
+    static ref FOOTNOTE_REFERENCE: Regex = Regex::new(
+        r#"(?is)<sup\b[^>]*>\s*<a\b[^>]*\bhref=["']#([^"']+)["'][^>]*>.*?</a>\s*</sup>|<a\b[^>]*\bhref=["']#([^"']+)["'][^>]*>\s*<sup\b[^>]*>.*?</sup>\s*</a>"#
+    )
+    .unwrap();
+}
+
+/// Footnote definitions keyed by the target of their references, with Editor.js
+/// inline HTML content
+pub(crate) type FootnoteDefinitions = HashMap<String, String>;
+
+/// Markup for a reference to the footnote with the given key
+pub(crate) fn reference_html(key: &str, label: &str) -> String {
+    format!(r##"<sup><a href="#{}">{}</a></sup>"##, key, label)
+}
+
+/// Keys of the footnotes referenced in the HTML, in order of appearance
+pub(crate) fn referenced_keys(html: &str) -> Vec<String> {
+    FOOTNOTE_REFERENCE
+        .captures_iter(html)
+        .map(|cap| reference_key(&cap).to_string())
+        .collect()
+}
+
+/// Replaces references to defined footnotes in the blocks' text with numbered
+/// references, and emits the footnotes either as a trailing ordered list with
+/// back-links or as `footnotes` tunes on the referencing blocks
+pub(crate) fn apply_footnotes(
+    blocks: Vec<EditorJsBlock>,
+    definitions: &FootnoteDefinitions,
+    options: &ConversionOptions,
+) -> Vec<EditorJsBlock> {
+    if definitions.is_empty() {
+        return blocks;
+    }
+
+    let mut order: Vec<String> = Vec::new();
+    let mut result = Vec::with_capacity(blocks.len() + 1);
+
+    for mut block in blocks {
+        let mut block_notes: Vec<usize> = Vec::new();
+
+        for text in block_texts_mut(&mut block) {
+            let replaced = FOOTNOTE_REFERENCE.replace_all(text, |cap: &Captures| {
+                let key = reference_key(cap);
+                if !definitions.contains_key(key) {
+                    return cap[0].to_string();
+                }
+
+                let number = match order.iter().position(|k| k == key) {
+                    Some(idx) => idx + 1,
+                    None => {
+                        order.push(key.to_string());
+                        order.len()
+                    }
+                };
+                if !block_notes.contains(&number) {
+                    block_notes.push(number);
+                }
+
+                match options.footnotes {
+                    FootnoteStyle::List => format!(
+                        r##"<sup><a href="#fn-{0}" id="fnref-{0}">{0}</a></sup>"##,
+                        number
+                    ),
+                    FootnoteStyle::Tune => format!(
+                        r#"<sup data-tune="footnotes" data-id="fn-{0}">{0}</sup>"#,
+                        number
+                    ),
+                }
+            });
+            *text = replaced.into_owned();
+        }
+
+        if options.footnotes == FootnoteStyle::Tune && !block_notes.is_empty() {
+            let footnotes = block_notes
+                .iter()
+                .map(|&number| Footnote {
+                    id: format!("fn-{}", number),
+                    content: definitions[&order[number - 1]].clone(),
+                    superscript: number as u32,
+                })
+                .collect();
+            block = EditorJsBlock::Tuned {
+                block: Box::new(block),
+                tunes: BlockTunes {
+                    footnotes: Some(footnotes),
+                },
+            };
+        }
+
+        result.push(block);
+    }
+
+    if options.footnotes == FootnoteStyle::List && !order.is_empty() {
+        let items = order
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| ListItem {
+                content: format!(
+                    r##"{} <a href="#fnref-{}">↩</a>"##,
+                    definitions[key],
+                    idx + 1
+                ),
+                meta: ListItemMeta::default(),
+                items: Vec::new(),
+            })
+            .collect();
+
+        result.push(EditorJsBlock::List {
+            data: ListData {
+                style: "ordered".to_string(),
+                items,
+                meta: Some(ListMeta {
+                    start: Some(1),
+                    counter_type: None,
+                }),
+            },
+        });
+    }
+
+    result
+}
+
+fn reference_key<'c>(cap: &Captures<'c>) -> &'c str {
+    cap.get(1).or(cap.get(2)).map_or("", |m| m.as_str())
+}
+
+/// The inline HTML text fields of a block, in reading order
+fn block_texts_mut(block: &mut EditorJsBlock) -> Vec<&mut String> {
+    fn item_texts_mut<'b>(items: &'b mut [ListItem], texts: &mut Vec<&'b mut String>) {
+        for item in items {
+            texts.push(&mut item.content);
+            item_texts_mut(&mut item.items, texts);
+        }
+    }
+
+    let mut texts = Vec::new();
+    match block {
+        EditorJsBlock::Paragraph { data } => texts.push(&mut data.text),
+        EditorJsBlock::Heading { data } => texts.push(&mut data.text),
+        EditorJsBlock::Quote { data } => texts.push(&mut data.text),
+        EditorJsBlock::Warning { data } => {
+            texts.push(&mut data.title);
+            texts.push(&mut data.message);
+        }
+        EditorJsBlock::List { data } => item_texts_mut(&mut data.items, &mut texts),
+        EditorJsBlock::Table { data } => texts.extend(data.content.iter_mut().flatten()),
+        EditorJsBlock::DefinitionList { data } => {
+            for item in &mut data.items {
+                texts.push(&mut item.term);
+                texts.extend(item.definitions.iter_mut());
+            }
+        }
+        EditorJsBlock::Toggle { data, children } => {
+            texts.push(&mut data.text);
+            texts.extend(children.iter_mut().flat_map(block_texts_mut));
+        }
+        EditorJsBlock::Tuned { block, .. } => texts = block_texts_mut(block),
+        _ => {}
+    }
+    texts
+}
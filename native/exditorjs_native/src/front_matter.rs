@@ -0,0 +1,495 @@
+//! Front matter extraction for Markdown documents
+
+use serde_json::{Map, Number, Value};
+
+/// Metadata parsed from a document's front matter
+pub type FrontMatter = Map<String, Value>;
+
+type FrontMatterParser = fn(&str) -> Option<FrontMatter>;
+
+/// Splits YAML (`---`) or TOML (`+++`) front matter from the start of a Markdown
+/// document, returning the parsed metadata and the remaining Markdown.
+///
+/// A block that cannot be parsed as metadata (such as a document starting with
+/// a horizontal rule) is left in place and `None` is returned.
+pub fn extract_front_matter(markdown: &str) -> (Option<FrontMatter>, &str) {
+    let content = markdown.strip_prefix('\u{feff}').unwrap_or(markdown);
+    let first_line = content.lines().next().unwrap_or("").trim_end();
+
+    let (closing, parse): (&[&str], FrontMatterParser) = match first_line {
+        "---" => (&["---", "..."], parse_yaml),
+        "+++" => (&["+++"], parse_toml),
+        _ => return (None, markdown),
+    };
+
+    let body_start = content.find('\n').map_or(content.len(), |idx| idx + 1);
+    let mut offset = body_start;
+    for line in content[body_start..].split_inclusive('\n') {
+        if closing.contains(&line.trim_end()) {
+            let metadata = match parse(&content[body_start..offset]) {
+                Some(metadata) => metadata,
+                None => return (None, markdown),
+            };
+            return (Some(metadata), &content[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+
+    (None, markdown)
+}
+
+fn parse_toml(source: &str) -> Option<FrontMatter> {
+    let table: toml::Table = toml::from_str(source).ok()?;
+    match toml_to_json(toml::Value::Table(table)) {
+        Value::Object(map) => Some(map),
+        _ => None,
+    }
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i.into()),
+        toml::Value::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Parses the subset of YAML used in front matter: nested mappings, block and
+/// flow sequences, quoted and plain scalars, block scalars (`|`, `>`) and
+/// comments. Anchors, aliases, tags, flow mappings and multi-line quoted
+/// scalars are not supported. Returns `None` for anything else.
+fn parse_yaml(source: &str) -> Option<FrontMatter> {
+    let raw: Vec<&str> = source.lines().collect();
+    // Blank and comment lines are skipped, except inside block scalars, which
+    // are read from the raw lines
+    let lines: Vec<YamlLine> = raw
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            let trimmed = line.trim();
+            !trimmed.is_empty() && !trimmed.starts_with('#')
+        })
+        .map(|(number, line)| YamlLine {
+            number,
+            indent: indentation(line),
+            text: line.trim().to_string(),
+        })
+        .collect();
+
+    if lines.is_empty() {
+        return Some(FrontMatter::new());
+    }
+    if lines[0].indent != 0 {
+        return None;
+    }
+
+    let mut parser = YamlParser { raw, lines, pos: 0 };
+    let value = parser.parse_mapping(0)?;
+    if parser.pos < parser.lines.len() {
+        return None;
+    }
+    match value {
+        Value::Object(map) => Some(map),
+        _ => None,
+    }
+}
+
+struct YamlLine {
+    /// Index of the line in the source
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+struct YamlParser<'a> {
+    raw: Vec<&'a str>,
+    lines: Vec<YamlLine>,
+    pos: usize,
+}
+
+impl YamlParser<'_> {
+    fn parse_block(&mut self, indent: usize) -> Option<Value> {
+        if self.lines[self.pos].text == "-" || self.lines[self.pos].text.starts_with("- ") {
+            self.parse_sequence(indent)
+        } else {
+            self.parse_mapping(indent)
+        }
+    }
+
+    fn parse_mapping(&mut self, indent: usize) -> Option<Value> {
+        let mut map = Map::new();
+
+        while self.pos < self.lines.len() && self.lines[self.pos].indent == indent {
+            let text = self.lines[self.pos].text.clone();
+            let (key, rest) = split_key_value(&text)?;
+            self.pos += 1;
+            let value = self.parse_value(rest, indent)?;
+            map.insert(key, value);
+        }
+
+        Some(Value::Object(map))
+    }
+
+    fn parse_sequence(&mut self, indent: usize) -> Option<Value> {
+        let mut items = Vec::new();
+
+        while self.pos < self.lines.len()
+            && self.lines[self.pos].indent == indent
+            && (self.lines[self.pos].text == "-" || self.lines[self.pos].text.starts_with("- "))
+        {
+            let item = self.lines[self.pos].text[1..].trim().to_string();
+            if split_key_value(&item).is_some() {
+                // `- key: value` starts a mapping nested in the sequence item
+                let item_indent = indent + 2;
+                self.lines[self.pos] = YamlLine {
+                    number: self.lines[self.pos].number,
+                    indent: item_indent,
+                    text: item,
+                };
+                items.push(self.parse_mapping(item_indent)?);
+            } else {
+                self.pos += 1;
+                items.push(self.parse_value(&item, indent)?);
+            }
+        }
+
+        Some(Value::Array(items))
+    }
+
+    /// Parses the value following `key:` or `-`, which may continue on the
+    /// following, more indented lines
+    fn parse_value(&mut self, rest: &str, indent: usize) -> Option<Value> {
+        let rest = strip_comment(rest);
+        let next_indent = self.lines.get(self.pos).map(|line| line.indent);
+
+        if rest.is_empty() {
+            return match next_indent {
+                Some(next) if next > indent => self.parse_block(next),
+                // A sequence may sit at the same indentation as its key
+                Some(next)
+                    if next == indent
+                        && (self.lines[self.pos].text == "-"
+                            || self.lines[self.pos].text.starts_with("- ")) =>
+                {
+                    self.parse_sequence(next)
+                }
+                _ => Some(Value::Null),
+            };
+        }
+
+        if rest == "|" || rest == ">" || rest.starts_with("|-") || rest.starts_with(">-") {
+            // The body starts on the line after the one holding the indicator
+            let start = self.lines[self.pos - 1].number + 1;
+            let (text, end) = block_scalar(&self.raw, start, indent, rest.starts_with('>'));
+            while self.pos < self.lines.len() && self.lines[self.pos].number < end {
+                self.pos += 1;
+            }
+            return Some(Value::String(text));
+        }
+
+        Some(parse_scalar(rest))
+    }
+}
+
+/// Reads the body of a block scalar from the raw lines starting at `start`:
+/// blank lines and lines indented deeper than `indent`. Indentation beyond that
+/// of the body is kept. Literal (`|`) bodies keep their line breaks; folded
+/// (`>`) bodies join lines with spaces, except around blank and more indented
+/// lines. Returns the text and the index of the line after the body.
+fn block_scalar(raw: &[&str], start: usize, indent: usize, folded: bool) -> (String, usize) {
+    let mut end = start;
+    while end < raw.len() && (raw[end].trim().is_empty() || indentation(raw[end]) > indent) {
+        end += 1;
+    }
+
+    let body = &raw[start..end];
+    let body_indent = body
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+    let mut lines: Vec<&str> = body
+        .iter()
+        .map(|line| line.get(body_indent..).unwrap_or(""))
+        .collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    if !folded {
+        return (lines.join("\n"), end);
+    }
+
+    let mut text = String::new();
+    let mut folds = false;
+    for line in lines {
+        if line.trim().is_empty() {
+            text.push('\n');
+            folds = false;
+            continue;
+        }
+        let more_indented = line.starts_with([' ', '\t']);
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push(if folds && !more_indented { ' ' } else { '\n' });
+        }
+        text.push_str(line);
+        folds = !more_indented;
+    }
+    (text, end)
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Splits `key: value` into its key and the (possibly empty) value
+fn split_key_value(text: &str) -> Option<(String, &str)> {
+    let (key, rest) = if let Some(quote @ ('"' | '\'')) = text.chars().next() {
+        let end = text[1..].find(quote)? + 1;
+        let rest = text[end + 1..].strip_prefix(':')?;
+        (text[1..end].to_string(), rest)
+    } else {
+        let idx = text
+            .match_indices(':')
+            .map(|(idx, _)| idx)
+            .find(|&idx| text[idx + 1..].is_empty() || text[idx + 1..].starts_with(' '))?;
+        (text[..idx].trim().to_string(), &text[idx + 1..])
+    };
+
+    if key.is_empty() || (!rest.is_empty() && !rest.starts_with(' ')) {
+        return None;
+    }
+    Some((key, rest.trim()))
+}
+
+fn strip_comment(value: &str) -> &str {
+    if let Some(end) = closing_quote(value) {
+        let rest = value[end + 1..].trim_start();
+        return if rest.is_empty() || rest.starts_with('#') {
+            &value[..=end]
+        } else {
+            value
+        };
+    }
+    match value.find(" #") {
+        Some(idx) => value[..idx].trim_end(),
+        None => value,
+    }
+}
+
+/// Offset of the quote closing a quoted scalar at the start of `value`
+fn closing_quote(value: &str) -> Option<usize> {
+    let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let mut chars = value.char_indices().skip(1).peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            // `''` is an escaped quote in single-quoted scalars
+            '\'' if quote == '\'' && chars.peek().is_some_and(|&(_, next)| next == '\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_scalar(value: &str) -> Value {
+    let value = value.trim();
+
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return Value::Array(
+            split_flow_items(inner)
+                .into_iter()
+                .map(parse_scalar)
+                .collect(),
+        );
+    }
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return Value::String(unescape_double_quoted(inner));
+    }
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return Value::String(inner.replace("''", "'"));
+    }
+
+    match value {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ => {
+            if let Ok(i) = value.parse::<i64>() {
+                Value::Number(i.into())
+            } else if let Some(n) = value
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .and_then(Number::from_f64)
+            {
+                Value::Number(n)
+            } else {
+                Value::String(value.to_string())
+            }
+        }
+    }
+}
+
+/// Resolves the escapes of a double-quoted scalar in a single pass: `\\`,
+/// `\"`, `\/`, `\0`, `\t`, `\n` and `\r`. Other escapes are kept verbatim.
+fn unescape_double_quoted(inner: &str) -> String {
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => result.push('\\'),
+            Some('"') => result.push('"'),
+            Some('/') => result.push('/'),
+            Some('0') => result.push('\0'),
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+/// Splits the items of a flow sequence on commas outside of quotes
+fn split_flow_items(inner: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+
+    for (idx, c) in inner.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (',', None) => {
+                items.push(inner[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(inner[start..].trim());
+    items.into_iter().filter(|item| !item.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let md = "---\ntitle: \"Kungsleden: the King's Trail\"\nauthor: Anna # editor\ndate: 2024-05-01\ndraft: false\nrating: 4.5\ntags: [hiking, 'lapland']\ncategories:\n  - trails\n  - sweden\nlocation:\n  lat: 67.9\n  lon: 18.5\n---\n# Heading\n";
+        let (meta, rest) = extract_front_matter(md);
+        let meta = meta.unwrap();
+
+        assert_eq!(meta["title"], json!("Kungsleden: the King's Trail"));
+        assert_eq!(meta["author"], json!("Anna"));
+        assert_eq!(meta["date"], json!("2024-05-01"));
+        assert_eq!(meta["draft"], json!(false));
+        assert_eq!(meta["rating"], json!(4.5));
+        assert_eq!(meta["tags"], json!(["hiking", "lapland"]));
+        assert_eq!(meta["categories"], json!(["trails", "sweden"]));
+        assert_eq!(meta["location"], json!({"lat": 67.9, "lon": 18.5}));
+        assert_eq!(rest, "# Heading\n");
+    }
+
+    #[test]
+    fn test_yaml_sequence_of_mappings_and_block_scalar() {
+        let md = "---\nauthors:\n- name: Anna\n  role: writer\n- name: Erik\nsummary: |\n  Line one\n  Line two\n---\nBody";
+        let (meta, rest) = extract_front_matter(md);
+        let meta = meta.unwrap();
+
+        assert_eq!(
+            meta["authors"],
+            json!([{"name": "Anna", "role": "writer"}, {"name": "Erik"}])
+        );
+        assert_eq!(meta["summary"], json!("Line one\nLine two"));
+        assert_eq!(rest, "Body");
+    }
+
+    #[test]
+    fn test_yaml_quoted_scalar_escapes() {
+        let md = "---\nwindows: \"C:\\\\new\"\nlines: \"one\\ntwo\\tend\"\nquote: \"say \\\"hej\\\"\"\nunknown: \"\\x41\"\nsingle: 'it''s C:\\new'\n---\n";
+        let (meta, _) = extract_front_matter(md);
+        let meta = meta.unwrap();
+
+        assert_eq!(meta["windows"], json!("C:\\new"));
+        assert_eq!(meta["lines"], json!("one\ntwo\tend"));
+        assert_eq!(meta["quote"], json!("say \"hej\""));
+        assert_eq!(meta["unknown"], json!("\\x41"));
+        assert_eq!(meta["single"], json!("it's C:\\new"));
+    }
+
+    #[test]
+    fn test_yaml_comments_after_quoted_scalars() {
+        let md = "---\ntitle: \"Hello\" # main title\nquote: 'it''s # not a comment' # but this is\nhash: \"a \\\" # b\"\n---\n";
+        let (meta, _) = extract_front_matter(md);
+        let meta = meta.unwrap();
+
+        assert_eq!(meta["title"], json!("Hello"));
+        assert_eq!(meta["quote"], json!("it's # not a comment"));
+        assert_eq!(meta["hash"], json!("a \" # b"));
+    }
+
+    #[test]
+    fn test_yaml_block_scalars_keep_comments_and_indentation() {
+        let md = "---\nsummary: |\n  # Heading\n  a\n    b\n\n  c\n# comment\nfolded: >-\n  one\n  two\n\n  three\n    code\n  four\ntitle: After\n---\n";
+        let (meta, _) = extract_front_matter(md);
+        let meta = meta.unwrap();
+
+        assert_eq!(meta["summary"], json!("# Heading\na\n  b\n\nc"));
+        assert_eq!(meta["folded"], json!("one two\nthree\n  code\nfour"));
+        assert_eq!(meta["title"], json!("After"));
+    }
+
+    #[test]
+    fn test_toml_front_matter() {
+        let md = "+++\ntitle = \"Trail guide\"\ndate = 2024-05-01\ntags = [\"hiking\"]\n[author]\nname = \"Anna\"\n+++\nBody";
+        let (meta, rest) = extract_front_matter(md);
+        let meta = meta.unwrap();
+
+        assert_eq!(meta["title"], json!("Trail guide"));
+        assert_eq!(meta["date"], json!("2024-05-01"));
+        assert_eq!(meta["tags"], json!(["hiking"]));
+        assert_eq!(meta["author"], json!({"name": "Anna"}));
+        assert_eq!(rest, "Body");
+    }
+
+    #[test]
+    fn test_horizontal_rules_are_not_front_matter() {
+        let md = "---\nJust some text between rules\n---\n";
+        let (meta, rest) = extract_front_matter(md);
+        assert!(meta.is_none());
+        assert_eq!(rest, md);
+
+        let md = "Text\n\n---\n\nMore";
+        assert!(extract_front_matter(md).0.is_none());
+    }
+}
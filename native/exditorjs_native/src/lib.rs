@@ -19,18 +19,38 @@
 extern crate rustler;
 
 pub mod blocks;
+mod code;
 pub mod embed;
 pub mod error;
+mod footnotes;
+pub mod front_matter;
 pub mod html;
+pub mod link_tool;
 pub mod markdown;
 pub mod models;
+pub mod options;
 
-pub use embed::{detect_embed_service, detect_service_from_src, parse_iframe};
+pub use embed::oembed::{OEmbed, OEmbedClient, OEmbedFetcher, OEmbedProviders};
+pub use embed::{
+    detect_embed_service, detect_service_from_src, media_type, parse_iframe, EmbedMatch,
+    EmbedRegistry, EmbedService, EmbedSize, ShortLinkResolver,
+};
 pub use error::{Error, Result};
-pub use html::html_to_editorjs;
-pub use markdown::markdown_to_editorjs;
+pub use front_matter::{extract_front_matter, FrontMatter};
+pub use html::{html_to_editorjs, html_to_editorjs_with_options};
+pub use link_tool::{populate_link_meta, LinkMetadataProvider};
+pub use markdown::{
+    markdown_to_editorjs, markdown_to_editorjs_with_front_matter, markdown_to_editorjs_with_options,
+};
 pub use models::{EditorJsBlock, EditorJsBlockWithId};
+pub use options::{
+    ConversionOptions, DefinitionListStyle, FootnoteStyle, LinkTarget, ListItemBlocks,
+    StandaloneLinkStyle,
+};
 use rustler::{Encoder, NifResult};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
 
 /// Represents an Editor.js document with proper structure
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -38,6 +58,9 @@ pub struct EditorJsDocument {
     pub time: i64,
     pub blocks: Vec<EditorJsBlockWithId>,
     pub version: String,
+    /// Document metadata, such as Markdown front matter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<FrontMatter>,
 }
 
 impl EditorJsDocument {
@@ -48,8 +71,17 @@ impl EditorJsDocument {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as i64,
-            blocks: blocks.into_iter().map(|b| b.with_id()).collect(),
+            blocks: blocks.into_iter().flat_map(|b| b.with_ids()).collect(),
             version: "2.25.0".to_string(),
+            meta: None,
+        }
+    }
+
+    /// Create a new Editor.js document from blocks with document metadata
+    pub fn with_meta(blocks: Vec<EditorJsBlock>, meta: FrontMatter) -> Self {
+        EditorJsDocument {
+            meta: Some(meta),
+            ..Self::new(blocks)
         }
     }
 }
@@ -62,10 +94,89 @@ mod atoms {
     }
 }
 
+/// Embed registry config from the Elixir application env: `{:json, config}`
+/// or `{:file, path}`
+#[derive(rustler::NifTaggedEnum, Clone, PartialEq, Eq, Hash)]
+enum EmbedConfig {
+    Json(String),
+    File(String),
+}
+
+/// Most embed registries kept at once; each config normally stays the same for
+/// the lifetime of the application, so this only matters when configs churn
+const MAX_EMBED_REGISTRIES: usize = 16;
+
+lazy_static::lazy_static! {
+    /// Embed registries by config, so that each config is read and its
+    /// patterns compiled once rather than on every conversion. File configs
+    /// are stored with the modification time they were read at.
+    static ref EMBED_REGISTRIES: Mutex<HashMap<EmbedConfig, (Option<SystemTime>, EmbedRegistry)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Embed registry for a config, built on first use and rebuilt when a config
+/// file changes
+fn embed_registry(config: EmbedConfig) -> Result<EmbedRegistry> {
+    let modified = match &config {
+        EmbedConfig::Json(_) => None,
+        EmbedConfig::File(path) => std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok(),
+    };
+
+    let cached = EMBED_REGISTRIES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&config)
+        .filter(|(read_at, _)| *read_at == modified)
+        .map(|(_, registry)| registry.clone());
+    if let Some(registry) = cached {
+        return Ok(registry);
+    }
+
+    let registry = match &config {
+        EmbedConfig::Json(json) => EmbedRegistry::from_json(json)?,
+        EmbedConfig::File(path) => EmbedRegistry::from_file(path)?,
+    };
+
+    let mut registries = EMBED_REGISTRIES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if registries.len() >= MAX_EMBED_REGISTRIES && !registries.contains_key(&config) {
+        if let Some(evicted) = registries.keys().next().cloned() {
+            registries.remove(&evicted);
+        }
+    }
+    registries.insert(config, (modified, registry.clone()));
+    Ok(registry)
+}
+
+/// Conversion options for the NIFs, with the configured embed registry
+fn nif_options(embeds: Option<EmbedConfig>) -> Result<ConversionOptions> {
+    let embeds = match embeds {
+        None => EmbedRegistry::default(),
+        Some(config) => embed_registry(config)?,
+    };
+
+    Ok(ConversionOptions {
+        embeds,
+        ..Default::default()
+    })
+}
+
 // NIF function to convert HTML to EditorJS
 #[rustler::nif(schedule = "DirtyCpu")]
-fn html_to_editorjs_nif(env: rustler::Env<'_>, html: String) -> NifResult<rustler::Term<'_>> {
-    match html_to_editorjs(&html) {
+fn html_to_editorjs_nif(
+    env: rustler::Env<'_>,
+    html: String,
+    embeds: Option<EmbedConfig>,
+) -> NifResult<rustler::Term<'_>> {
+    let options = match nif_options(embeds) {
+        Ok(options) => options,
+        Err(e) => return Ok((atoms::error(), e.to_string()).encode(env)),
+    };
+
+    match html_to_editorjs_with_options(&html, &options) {
         Ok(blocks) => {
             let document = EditorJsDocument::new(blocks);
             match serde_json::to_string(&document) {
@@ -86,10 +197,20 @@ fn html_to_editorjs_nif(env: rustler::Env<'_>, html: String) -> NifResult<rustle
 fn markdown_to_editorjs_nif(
     env: rustler::Env<'_>,
     markdown: String,
+    embeds: Option<EmbedConfig>,
 ) -> NifResult<rustler::Term<'_>> {
-    match markdown_to_editorjs(&markdown) {
-        Ok(blocks) => {
-            let document = EditorJsDocument::new(blocks);
+    let options = match nif_options(embeds) {
+        Ok(options) => options,
+        Err(e) => return Ok((atoms::error(), e.to_string()).encode(env)),
+    };
+
+    match markdown_to_editorjs_with_front_matter(&markdown, &options) {
+        Ok((blocks, meta)) => {
+            let document = if meta.is_empty() {
+                EditorJsDocument::new(blocks)
+            } else {
+                EditorJsDocument::with_meta(blocks, meta)
+            };
             match serde_json::to_string(&document) {
                 Ok(json) => Ok((atoms::ok(), json).encode(env)),
                 Err(_) => Err(rustler::error::Error::RaiseTerm(Box::new(
@@ -104,3 +225,63 @@ fn markdown_to_editorjs_nif(
 }
 
 rustler::init!("Elixir.ExditorJS");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_config_file_is_reloaded_when_modified() {
+        let path =
+            std::env::temp_dir().join(format!("exditorjs-embeds-{}.toml", std::process::id()));
+        let write_config = |name: &str, modified: SystemTime| {
+            std::fs::write(
+                &path,
+                format!("[[services]]\nname = \"{}\"\npattern = 'maps\\.example\\.com/t/(\\w+)'\nembed_url = \"https://maps.example.com/embed/{{}}\"\n", name),
+            )
+            .unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        let config = EmbedConfig::File(path.to_string_lossy().into_owned());
+        let service = |config: &EmbedConfig| {
+            nif_options(Some(config.clone()))
+                .unwrap()
+                .embeds
+                .detect("https://maps.example.com/t/kebnekaise")
+                .unwrap()
+                .service
+        };
+
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        write_config("trailmap", modified);
+        assert_eq!(service(&config), "trailmap");
+
+        write_config("hikemap", modified + std::time::Duration::from_secs(60));
+        assert_eq!(service(&config), "hikemap");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(nif_options(Some(config)).is_err());
+        assert!(nif_options(Some(EmbedConfig::Json("{".to_string()))).is_err());
+    }
+
+    #[test]
+    fn test_embed_registry_cache_is_bounded() {
+        for i in 0..MAX_EMBED_REGISTRIES + 4 {
+            let json = format!(
+                r#"{{"services": [{{"name": "svc{}", "pattern": "svc{}\\.example\\.com/(\\w+)", "embed_url": "https://svc{}.example.com/{{}}"}}]}}"#,
+                i, i, i
+            );
+            assert!(nif_options(Some(EmbedConfig::Json(json))).is_ok());
+        }
+
+        let registries = EMBED_REGISTRIES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        assert!(registries.len() <= MAX_EMBED_REGISTRIES);
+    }
+}
@@ -0,0 +1,131 @@
+//! LinkTool blocks for standalone links
+//!
+//! Conversion leaves the metadata of link previews empty. Fetching pages is up
+//! to the caller, who fills it in with a [`LinkMetadataProvider`].
+
+use crate::models::{EditorJsBlock, LinkMeta, LinkToolData};
+use regex::Regex;
+
+lazy_static::lazy_static! {
+    static ref STANDALONE_LINK: Regex = Regex::new(
+        r#"(?is)^\s*(?:<a\s[^>]*?href\s*=\s*["'](https?://[^"']+)["'][^>]*>(?:[^<]|<[^/aA]|</[^aA])*</a>|<?(https?://[^\s<>"]+?)>?)\s*$"#
+    )
+    .unwrap();
+}
+
+/// Supplies the title, description and image of linked pages
+pub trait LinkMetadataProvider {
+    /// Metadata for `url`, or `None` when it is unavailable
+    fn metadata(&self, url: &str) -> Option<LinkMeta>;
+}
+
+impl<F> LinkMetadataProvider for F
+where
+    F: Fn(&str) -> Option<LinkMeta>,
+{
+    fn metadata(&self, url: &str) -> Option<LinkMeta> {
+        self(url)
+    }
+}
+
+/// Fills the empty metadata of link previews, including those inside toggles
+pub fn populate_link_meta<P>(blocks: &mut [EditorJsBlock], provider: &P)
+where
+    P: LinkMetadataProvider + ?Sized,
+{
+    for block in blocks {
+        match block {
+            EditorJsBlock::LinkTool { data } if data.meta == LinkMeta::default() => {
+                if let Some(meta) = provider.metadata(&data.link) {
+                    data.meta = meta;
+                }
+            }
+            EditorJsBlock::Toggle { children, .. } => populate_link_meta(children, provider),
+            EditorJsBlock::Tuned { block, .. } => {
+                populate_link_meta(std::slice::from_mut(block.as_mut()), provider)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// URL of HTML text that is nothing but a bare URL or a single link
+pub(crate) fn standalone_link(html: &str) -> Option<String> {
+    let caps = STANDALONE_LINK.captures(html)?;
+    caps.get(1)
+        .or_else(|| caps.get(2))
+        .map(|url| url.as_str().replace("&amp;", "&"))
+}
+
+pub(crate) fn link_tool_block(url: &str) -> EditorJsBlock {
+    EditorJsBlock::LinkTool {
+        data: LinkToolData {
+            link: url.to_string(),
+            meta: LinkMeta::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LinkImage;
+
+    #[test]
+    fn test_standalone_link() {
+        assert_eq!(
+            standalone_link(
+                r#" <a href="https://example.com/a?b=1&amp;c=2" target="_blank">Example</a> "#
+            ),
+            Some("https://example.com/a?b=1&c=2".to_string())
+        );
+        assert_eq!(
+            standalone_link("<https://example.com/>"),
+            Some("https://example.com/".to_string())
+        );
+        assert_eq!(standalone_link("See https://example.com/"), None);
+        assert_eq!(
+            standalone_link(
+                r#"<a href="https://a.example/">A</a> <a href="https://b.example/">B</a>"#
+            ),
+            None
+        );
+        assert_eq!(standalone_link(r#"<a href="/relative">Relative</a>"#), None);
+    }
+
+    #[test]
+    fn test_populate_link_meta() {
+        let provider = |url: &str| {
+            (url == "https://example.com/").then(|| LinkMeta {
+                title: Some("Example".to_string()),
+                description: None,
+                image: Some(LinkImage {
+                    url: "https://example.com/og.png".to_string(),
+                }),
+            })
+        };
+
+        let mut blocks = vec![
+            link_tool_block("https://example.com/"),
+            link_tool_block("https://unknown.example/"),
+        ];
+        populate_link_meta(&mut blocks, &provider);
+
+        let metas: Vec<_> = blocks
+            .iter()
+            .map(|block| match block {
+                EditorJsBlock::LinkTool { data } => data.meta.title.as_deref(),
+                other => panic!("Expected link tool block, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(metas, vec![Some("Example"), None]);
+
+        let json = serde_json::to_value(&blocks).unwrap();
+        assert_eq!(json[0]["type"], "linkTool");
+        assert_eq!(
+            json[0]["data"]["meta"]["image"]["url"],
+            "https://example.com/og.png"
+        );
+        assert_eq!(json[1]["data"]["meta"], serde_json::json!({}));
+    }
+}
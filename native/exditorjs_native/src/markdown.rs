@@ -1,29 +1,133 @@
-use crate::embed::detect_embed_service;
+use crate::code::{code_block, language_from_info_string};
+use crate::embed::{media_block, parse_snippet_html};
 use crate::error::Result;
+use crate::footnotes::{apply_footnotes, FootnoteDefinitions};
+use crate::front_matter::{extract_front_matter, FrontMatter};
+use crate::link_tool::link_tool_block;
 use crate::models::*;
+use crate::options::{ConversionOptions, ListItemBlocks, StandaloneLinkStyle};
+use inline::{footnote_key, normalize_label, InlineRenderer, LinkReference, LinkReferences};
 use regex::Regex;
 
+mod inline;
+
+lazy_static::lazy_static! {
+    static ref LIST_MARKER: Regex = Regex::new(r"^([ \t]*)([*+-]|\d{1,9}[.)])([ \t]+|$)").unwrap();
+    static ref ALERT_MARKER: Regex =
+        Regex::new(r"(?i)^\[!(NOTE|TIP|IMPORTANT|WARNING|CAUTION)\](?:[ \t]+(.*))?$").unwrap();
+    static ref ADMONITION: Regex =
+        Regex::new(r#"^!!![ \t]+([\w-]+)(?:[ \t]+"([^"]*)")?[ \t]*$"#).unwrap();
+    static ref FOOTNOTE_DEFINITION: Regex =
+        Regex::new(r#"^ {0,3}\[\^([^\]\s"'<>\[]+)\]:[ \t]*(.*)$"#).unwrap();
+    static ref DETAILS_OPEN: Regex = Regex::new(r"(?i)^[ \t]*<details(\s[^>]*)?>").unwrap();
+    static ref SNIPPET_START: Regex = Regex::new(r"(?i)^<(?:blockquote|div|script)\b").unwrap();
+    static ref DETAILS_TAG: Regex = Regex::new(r"(?i)<(/?)details(?:\s[^>]*)?>").unwrap();
+    static ref SUMMARY: Regex = Regex::new(r"(?is)^\s*<summary(?:\s[^>]*)?>(.*?)</summary>").unwrap();
+    static ref DEFINITION_MARKER: Regex = Regex::new(r"^ {0,3}:[ \t]+(.*)$").unwrap();
+    static ref TASK_MARKER: Regex = Regex::new(r"^\[([ xX])\](?:[ \t]+|$)").unwrap();
+    static ref LINK_DEFINITION: Regex = Regex::new(
+        r#"^ {0,3}\[([^\]^][^\]]*)\]:\s*(?:<([^>]*)>|(\S+))(?:\s+(?:"([^"]*)"|'([^']*)'|\(([^)]*)\)))?\s*$"#
+    )
+    .unwrap();
+}
+
 /// Convert Markdown to Editor.js blocks
 pub fn markdown_to_editorjs(markdown: &str) -> Result<Vec<EditorJsBlock>> {
-    let parser = MarkdownParser::new(markdown);
-    parser.parse()
+    markdown_to_editorjs_with_options(markdown, &ConversionOptions::default())
 }
 
-struct MarkdownParser {
+/// Convert Markdown to Editor.js blocks using the given conversion options
+pub fn markdown_to_editorjs_with_options(
+    markdown: &str,
+    options: &ConversionOptions,
+) -> Result<Vec<EditorJsBlock>> {
+    markdown_to_editorjs_with_front_matter(markdown, options).map(|(blocks, _)| blocks)
+}
+
+/// Convert Markdown to Editor.js blocks, returning the document's YAML or TOML
+/// front matter alongside them (empty when the document has none)
+pub fn markdown_to_editorjs_with_front_matter(
+    markdown: &str,
+    options: &ConversionOptions,
+) -> Result<(Vec<EditorJsBlock>, FrontMatter)> {
+    let (front_matter, content) = extract_front_matter(markdown);
+    let parser = MarkdownParser::new(content, options);
+    let blocks = apply_footnotes(parser.parse()?, &parser.footnote_definitions(), options);
+    Ok((blocks, front_matter.unwrap_or_default()))
+}
+
+struct MarkdownParser<'a> {
     #[allow(dead_code)]
     markdown: String,
     lines: Vec<String>,
+    options: &'a ConversionOptions,
+    /// Link reference definitions of the whole document
+    references: LinkReferences,
+    /// Footnote definitions of the whole document, as Markdown
+    footnotes: FootnoteDefinitions,
+    /// Set when parsing the content of a list item or warning
+    nested: bool,
 }
 
-impl MarkdownParser {
-    fn new(markdown: &str) -> Self {
-        let lines: Vec<String> = markdown.lines().map(|s| s.to_string()).collect();
+impl<'a> MarkdownParser<'a> {
+    fn new(markdown: &str, options: &'a ConversionOptions) -> Self {
+        let mut lines: Vec<String> = markdown.lines().map(|s| s.to_string()).collect();
+        let footnotes = extract_footnote_definitions(&mut lines);
+        let references = extract_link_references(&mut lines);
         MarkdownParser {
             markdown: markdown.to_string(),
             lines,
+            options,
+            references,
+            footnotes,
+            nested: false,
+        }
+    }
+
+    /// Creates a parser for block content nested in a list item or warning
+    fn nested_parser(&self, lines: Vec<String>) -> MarkdownParser<'a> {
+        MarkdownParser {
+            markdown: String::new(),
+            lines,
+            options: self.options,
+            references: self.references.clone(),
+            footnotes: self.footnotes.clone(),
+            nested: true,
         }
     }
 
+    fn render_inline(&self, text: &str) -> String {
+        InlineRenderer::new(&self.references, self.options)
+            .with_footnotes(&self.footnotes)
+            .render(text)
+    }
+
+    /// Footnote definitions rendered as Editor.js inline HTML
+    fn footnote_definitions(&self) -> FootnoteDefinitions {
+        self.footnotes
+            .iter()
+            .map(|(key, markdown)| {
+                let lines = markdown.lines().map(|line| line.to_string()).collect();
+                (key.clone(), self.block_text(lines))
+            })
+            .collect()
+    }
+
+    /// Text of nested Markdown content, with paragraphs and other blocks on
+    /// separate lines
+    fn block_text(&self, lines: Vec<String>) -> String {
+        self.nested_parser(lines)
+            .parse()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|block| match block {
+                EditorJsBlock::Paragraph { data } => Some(data.text.clone()),
+                block => inline_block_text(block),
+            })
+            .collect::<Vec<_>>()
+            .join("<br>")
+    }
+
     fn parse(&self) -> Result<Vec<EditorJsBlock>> {
         let mut blocks = Vec::new();
         let mut i = 0;
@@ -31,11 +135,19 @@ impl MarkdownParser {
         while i < self.lines.len() {
             let line = &self.lines[i];
 
-            if line.is_empty() {
+            if line.trim().is_empty() {
                 i += 1;
                 continue;
             }
 
+            // Check for indented code blocks
+            if self.indent_width(line) >= 4 {
+                let (code_block, next_i) = self.parse_indented_code_block(i);
+                blocks.push(code_block);
+                i = next_i;
+                continue;
+            }
+
             // Check for headings
             if let Some(level) = self.parse_heading_level(line) {
                 let text = self.extract_heading_text(line, level);
@@ -46,28 +158,60 @@ impl MarkdownParser {
                 continue;
             }
 
-            // Check for code blocks
-            if line.starts_with("```") {
-                let (code_block, next_i) = self.parse_code_block(i);
-                if let Some(block) = code_block {
-                    blocks.push(block);
+            // Check for fenced code blocks
+            if let Some(fence) = code_fence(line) {
+                let (code_block, next_i) = self.parse_code_block(i, &fence);
+                blocks.push(code_block);
+                i = next_i;
+                continue;
+            }
+
+            // Check for display math
+            if line.trim_start().starts_with("$$") {
+                if let Some((math_block, next_i)) = self.parse_math_block(i) {
+                    blocks.push(math_block);
+                    i = next_i;
+                    continue;
                 }
+            }
+
+            // Check for raw `<details>` HTML
+            if let Some((toggle, next_i)) = self.parse_details(i) {
+                blocks.push(toggle);
+                i = next_i;
+                continue;
+            }
+
+            // Check for pasted embed snippets of social media posts
+            if let Some((mut embed_blocks, next_i)) =
+                self.parse_embed_snippet(i).filter(|_| !self.nested)
+            {
+                blocks.append(&mut embed_blocks);
                 i = next_i;
                 continue;
             }
 
             // Check for lists (unordered, ordered, or checklist)
             if self.is_list_start(line) {
-                let (list_block, next_i) = self.parse_list(i);
-                blocks.push(list_block);
+                let (mut list_blocks, next_i) = self.parse_list(i);
+                blocks.append(&mut list_blocks);
+                i = next_i;
+                continue;
+            }
+
+            // Check for GitHub alerts (`> [!NOTE]`) and admonitions (`!!! note`)
+            if let Some((warning, next_i)) =
+                self.parse_alert(i).or_else(|| self.parse_admonition(i))
+            {
+                blocks.push(warning);
                 i = next_i;
                 continue;
             }
 
             // Check for blockquotes
-            if line.trim_start().starts_with("> ") {
-                let (quote_block, next_i) = self.parse_blockquote(i);
-                blocks.push(quote_block);
+            if line.trim_start().starts_with('>') {
+                let (mut quote_blocks, next_i) = self.parse_blockquote(i);
+                blocks.append(&mut quote_blocks);
                 i = next_i;
                 continue;
             }
@@ -86,8 +230,8 @@ impl MarkdownParser {
                 continue;
             }
 
-            // Check for embed links
-            if let Some(embed_block) = self.parse_embed_link(line) {
+            // Check for embed links. Links inside list items stay part of the item text.
+            if let Some(embed_block) = self.parse_embed_link(line).filter(|_| !self.nested) {
                 blocks.push(embed_block);
                 i += 1;
                 continue;
@@ -101,11 +245,16 @@ impl MarkdownParser {
                 continue;
             }
 
+            // Check for definition lists (`Term` followed by `: Definition`)
+            if let Some((list_block, next_i)) = self.parse_definition_list(i) {
+                blocks.push(list_block);
+                i = next_i;
+                continue;
+            }
+
             // Treat as paragraph
             let (paragraph, next_i) = self.parse_paragraph(i);
-            blocks.push(EditorJsBlock::Paragraph {
-                data: ParagraphData { text: paragraph },
-            });
+            blocks.extend(paragraph_blocks(&paragraph, self.options));
             i = next_i;
         }
 
@@ -113,33 +262,50 @@ impl MarkdownParser {
     }
 
     fn is_list_start(&self, line: &str) -> bool {
-        let trimmed = line.trim_start();
-
-        // Unordered list: -, +, *
-        if trimmed.starts_with("- ") || trimmed.starts_with("+ ") || trimmed.starts_with("* ") {
-            return true;
-        }
+        self.split_list_marker(line).is_some()
+    }
 
-        // Ordered list: 1. 2. etc
-        if let Some(dot_pos) = trimmed.find(". ") {
-            if trimmed[..dot_pos].chars().all(|c| c.is_numeric()) {
-                return true;
-            }
-        }
+    /// Splits a list item line into its marker and content. Returns the marker,
+    /// the column at which the item content starts, and the content itself.
+    fn split_list_marker<'l>(&self, line: &'l str) -> Option<(&'l str, usize, &'l str)> {
+        let cap = LIST_MARKER.captures(line)?;
+        let indent = self.indent_width(&cap[1]);
+        let marker = cap.get(2).unwrap().as_str();
+        let content = &line[cap.get(0).unwrap().end()..];
+
+        // Content indented by five or more spaces starts with an indented code
+        // block, so the item content begins one space after the marker
+        let spacing = self.indent_width(&cap[3]);
+        let spacing = if spacing == 0 || spacing > 4 || content.trim().is_empty() {
+            1
+        } else {
+            spacing
+        };
 
-        // Checklist: - [ ] or - [x]
-        if trimmed.starts_with("- [") || trimmed.starts_with("+ [") || trimmed.starts_with("* [") {
-            return true;
-        }
+        Some((marker, indent + marker.len() + spacing, content))
+    }
 
-        false
+    /// Width of the leading whitespace of a line, with tabs counted as four columns
+    fn indent_width(&self, line: &str) -> usize {
+        line.chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum()
     }
 
-    fn get_list_indent(&self, line: &str) -> usize {
-        line.len() - line.trim_start().len()
+    /// Removes up to `columns` columns of leading whitespace from a line
+    fn strip_indent(&self, line: &str, columns: usize) -> String {
+        let mut width = 0;
+        for (idx, c) in line.char_indices() {
+            if width >= columns || !(c == ' ' || c == '\t') {
+                return line[idx..].to_string();
+            }
+            width += if c == '\t' { 4 } else { 1 };
+        }
+        String::new()
     }
 
-    fn parse_list(&self, start: usize) -> (EditorJsBlock, usize) {
+    fn parse_list(&self, start: usize) -> (Vec<EditorJsBlock>, usize) {
         let first_line = &self.lines[start];
         let trimmed = first_line.trim_start();
 
@@ -151,7 +317,11 @@ impl MarkdownParser {
             "unordered"
         };
 
-        let (items, next_i) = self.parse_list_items(start, style);
+        let mut hoisted = Vec::new();
+        let (mut items, next_i) = self.parse_list_items(start, style, &mut hoisted);
+        if style == "checklist" {
+            mark_unchecked_items(&mut items);
+        }
 
         let data = if style == "ordered" {
             ListData {
@@ -170,131 +340,196 @@ impl MarkdownParser {
             }
         };
 
-        (EditorJsBlock::List { data }, next_i)
+        let mut blocks = vec![EditorJsBlock::List { data }];
+        blocks.append(&mut hoisted);
+        (blocks, next_i)
     }
 
     fn is_checklist_item(&self, line: &str) -> bool {
-        let trimmed = line.trim_start();
-        trimmed.contains("- [") || trimmed.contains("+ [") || trimmed.contains("* [")
+        // Only `[ ]`, `[x]` and `[X]` count as task markers, so that items
+        // starting with a link (`- [text](url)`) stay regular list items
+        match self.split_list_marker(line) {
+            Some((marker, _, content)) => {
+                !marker.ends_with(['.', ')']) && self.parse_task_marker(content).1.is_some()
+            }
+            None => false,
+        }
     }
 
     fn is_ordered_list_item(&self, line: &str) -> bool {
-        if let Some(dot_pos) = line.find(". ") {
-            line[..dot_pos].chars().all(|c| c.is_numeric())
-        } else {
-            false
-        }
+        self.split_list_marker(line)
+            .is_some_and(|(marker, _, _)| marker.ends_with(['.', ')']))
     }
 
-    fn parse_list_items(&self, start: usize, style: &str) -> (Vec<ListItem>, usize) {
+    fn parse_list_items(
+        &self,
+        start: usize,
+        style: &str,
+        hoisted: &mut Vec<EditorJsBlock>,
+    ) -> (Vec<ListItem>, usize) {
         let mut items = Vec::new();
         let mut i = start;
-        let base_indent = self.get_list_indent(&self.lines[start]);
+        let base_indent = self.indent_width(&self.lines[start]);
 
         while i < self.lines.len() {
             let line = &self.lines[i];
 
-            if line.is_empty() {
-                i += 1;
-                continue;
-            }
-
-            let current_indent = self.get_list_indent(line);
-            let trimmed = line.trim_start();
-
-            // Check if this line is part of the current list
-            if !self.is_list_start(line) {
-                break;
+            // Blank lines between items keep the list going (a "loose" list)
+            if line.trim().is_empty() {
+                let next = (i..self.lines.len()).find(|&j| !self.lines[j].trim().is_empty());
+                match next {
+                    Some(j)
+                        if self.is_list_start(&self.lines[j])
+                            && self.indent_width(&self.lines[j]) >= base_indent =>
+                    {
+                        i = j;
+                        continue;
+                    }
+                    _ => break,
+                }
             }
 
-            // If indentation is less than base, we've finished this list
-            if current_indent < base_indent && !trimmed.is_empty() {
+            // Anything that is not a sibling item ends this list. Deeper items
+            // never reach this point as they belong to the previous item.
+            if !self.is_list_start(line) || self.indent_width(line) < base_indent {
                 break;
             }
 
-            // If same indent level as base
-            if current_indent == base_indent {
-                let (item, next_i) = self.parse_list_item(i, style);
-                items.push(item);
-                i = next_i;
-            } else if current_indent > base_indent {
-                // Nested item - add to last item
-                if let Some(last_item) = items.last_mut() {
-                    let (nested_items, next_i) = self.parse_list_items(i, style);
-                    last_item.items = nested_items;
-                    i = next_i;
-                } else {
-                    i += 1;
-                }
-            } else {
-                break;
-            }
+            let (item, next_i) = self.parse_list_item(i, style, hoisted);
+            items.push(item);
+            i = next_i;
         }
 
         (items, i)
     }
 
-    fn parse_list_item(&self, index: usize, style: &str) -> (ListItem, usize) {
+    /// Parses a list item together with its continuation lines. Lines indented
+    /// to the item's content column (and lazy paragraph continuation lines) are
+    /// parsed as Markdown of their own: paragraphs become lines of the item text,
+    /// nested lists become child items and other blocks are handled according to
+    /// [`ConversionOptions::list_item_blocks`].
+    fn parse_list_item(
+        &self,
+        index: usize,
+        style: &str,
+        hoisted: &mut Vec<EditorJsBlock>,
+    ) -> (ListItem, usize) {
         let line = &self.lines[index];
-        let trimmed = line.trim_start();
-
-        let (content, checked) = if style == "checklist" {
-            self.parse_checklist_item(trimmed)
-        } else if style == "ordered" {
-            (self.parse_ordered_list_item(trimmed), None)
+        let (_, content_column, first_content) = self.split_list_marker(line).unwrap_or((
+            "",
+            self.indent_width(line),
+            line.trim_start(),
+        ));
+
+        let (first_content, checked) = if style == "checklist" {
+            let (content, checked) = self.parse_task_marker(first_content);
+            (content, Some(checked.unwrap_or(false)))
         } else {
-            (self.parse_unordered_list_item(trimmed), None)
+            (first_content, None)
         };
 
-        let mut formatted_content = self.convert_inline_formatting(&content);
-        formatted_content = self.convert_markdown_links(&formatted_content);
+        let mut body = vec![first_content.to_string()];
+        let mut in_fence = self.is_code_fence(first_content);
+        let mut i = index + 1;
 
-        let meta = if let Some(checked_val) = checked {
-            Some(ListItemMeta {
-                checked: Some(checked_val),
-            })
-        } else {
-            Some(ListItemMeta { checked: None })
-        };
+        while i < self.lines.len() {
+            let line = &self.lines[i];
+
+            if line.trim().is_empty() {
+                // Blank lines only belong to the item if indented content follows
+                let next = (i..self.lines.len()).find(|&j| !self.lines[j].trim().is_empty());
+                match next {
+                    Some(j) if self.indent_width(&self.lines[j]) >= content_column => {
+                        body.extend(std::iter::repeat_n(String::new(), j - i));
+                        i = j;
+                        continue;
+                    }
+                    _ => break,
+                }
+            }
+
+            if self.indent_width(line) >= content_column {
+                let stripped = self.strip_indent(line, content_column);
+                if self.is_code_fence(&stripped) {
+                    in_fence = !in_fence;
+                }
+                body.push(stripped);
+                i += 1;
+                continue;
+            }
+
+            // Lazy continuation: an unindented line continues the item paragraph
+            let continues_paragraph =
+                !in_fence && body.last().is_some_and(|l| !l.trim().is_empty());
+            if continues_paragraph && !self.is_list_start(line) && !self.starts_block(i) {
+                body.push(line.trim().to_string());
+                i += 1;
+                continue;
+            }
+
+            break;
+        }
+
+        let blocks = self.nested_parser(body).parse().unwrap_or_default();
+
+        let mut paragraphs = Vec::new();
+        let mut nested_items = Vec::new();
+        for block in blocks {
+            match block {
+                EditorJsBlock::Paragraph { data } => paragraphs.push(data.text),
+                EditorJsBlock::List { data } => nested_items.extend(data.items),
+                block => match self.options.list_item_blocks {
+                    ListItemBlocks::Hoist => hoisted.push(block),
+                    ListItemBlocks::Inline => paragraphs.extend(inline_block_text(&block)),
+                },
+            }
+        }
 
         (
             ListItem {
-                content: formatted_content,
-                meta: meta.unwrap_or_default(),
-                items: Vec::new(),
+                content: paragraphs.join("<br>"),
+                meta: ListItemMeta { checked },
+                items: nested_items,
             },
-            index + 1,
+            i,
         )
     }
 
-    fn parse_unordered_list_item(&self, line: &str) -> String {
-        for prefix in &["-", "+", "*"] {
-            if let Some(stripped) = line.strip_prefix(&format!("{} ", prefix)) {
-                return stripped.trim().to_string();
+    /// Strips a `[ ]`/`[x]` task marker from list item content, returning the
+    /// remaining content and the checked state when a marker was present
+    fn parse_task_marker<'l>(&self, content: &'l str) -> (&'l str, Option<bool>) {
+        match TASK_MARKER.captures(content) {
+            Some(cap) => {
+                let checked = cap[1].eq_ignore_ascii_case("x");
+                (&content[cap.get(0).unwrap().end()..], Some(checked))
             }
+            None => (content, None),
         }
-        line.to_string()
     }
 
-    fn parse_ordered_list_item(&self, line: &str) -> String {
-        if let Some(dot_pos) = line.find(". ") {
-            line[dot_pos + 2..].trim().to_string()
-        } else {
-            line.to_string()
-        }
+    fn is_code_fence(&self, line: &str) -> bool {
+        code_fence(line).is_some()
     }
 
-    fn parse_checklist_item(&self, line: &str) -> (String, Option<bool>) {
-        // Pattern: - [ ] text or - [x] text or - [X] text
-        let re = Regex::new(r"^[*+-]\s+\[([^\]]*)\]\s+(.*)$").unwrap();
-        if let Some(cap) = re.captures(line) {
-            let checkbox = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let content = cap.get(2).map(|m| m.as_str()).unwrap_or("").to_string();
-            let checked = checkbox.to_lowercase() == "x";
-            (content, Some(checked))
-        } else {
-            (line.to_string(), Some(false))
-        }
+    /// Whether a line starts a block that interrupts a paragraph
+    /// Whether line `i` starts a block that interrupts a paragraph
+    fn starts_block(&self, i: usize) -> bool {
+        let line = &self.lines[i];
+        let trimmed = line.trim_start();
+        // Ordered lists only interrupt a paragraph when they start at 1, so that
+        // lines like "2024. A good year" stay part of the text
+        let starts_list = self.split_list_marker(line).is_some_and(|(marker, _, _)| {
+            !marker.ends_with(['.', ')']) || marker.len() == 2 && marker.starts_with('1')
+        });
+
+        self.parse_heading_level(line).is_some()
+            || self.is_code_fence(line)
+            || starts_list
+            || trimmed.starts_with('>')
+            || (trimmed.starts_with("$$") && self.parse_math_block(i).is_some())
+            || ADMONITION.is_match(line.trim_end())
+            || DETAILS_OPEN.is_match(line)
+            || self.is_horizontal_rule(line)
     }
 
     fn parse_heading_level(&self, line: &str) -> Option<u8> {
@@ -316,64 +551,395 @@ impl MarkdownParser {
             .trim()
             .to_string();
 
-        text = self.convert_inline_formatting(&text);
-        text = self.convert_markdown_links(&text);
+        text = self.render_inline(&text);
         text
     }
 
-    fn parse_code_block(&self, start: usize) -> (Option<EditorJsBlock>, usize) {
+    /// Parses a fenced code block, which ends at a closing fence of the same
+    /// character that is at least as long as the opening one (or at the end of
+    /// the document)
+    fn parse_code_block(&self, start: usize, fence: &CodeFence) -> (EditorJsBlock, usize) {
         let mut code_lines = Vec::new();
-        let mut language = String::new();
         let mut i = start + 1;
 
-        let first_line = &self.lines[start];
-        if first_line.len() > 3 {
-            language = first_line[3..].trim().to_string();
+        while i < self.lines.len() && !fence.is_closed_by(&self.lines[i]) {
+            // Content lines lose the indentation of the opening fence
+            code_lines.push(self.strip_indent(&self.lines[i], fence.indent));
+            i += 1;
         }
 
-        while i < self.lines.len() && !self.lines[i].trim().starts_with("```") {
-            code_lines.push(self.lines[i].clone());
+        let code = code_lines.join("\n");
+        let language = language_from_info_string(fence.info);
+        let block = if language.as_deref() == Some("math") {
+            EditorJsBlock::Math {
+                data: MathData { text: code },
+            }
+        } else {
+            code_block(code, language, self.options)
+        };
+
+        (block, if i < self.lines.len() { i + 1 } else { i })
+    }
+
+    /// Parses `$$ ... $$` display math, on one line or spanning several. An
+    /// unclosed `$$` is not math.
+    fn parse_math_block(&self, start: usize) -> Option<(EditorJsBlock, usize)> {
+        let first = self.lines[start].trim().strip_prefix("$$")?;
+        let mut tex = Vec::new();
+        let mut i = start;
+        let mut line = first;
+
+        loop {
+            if let Some(content) = line.trim_end().strip_suffix("$$") {
+                tex.push(content);
+                break;
+            }
+            tex.push(line);
             i += 1;
+            line = self.lines.get(i)?;
+        }
+
+        let text = tex.join("\n").trim().to_string();
+        Some((
+            EditorJsBlock::Math {
+                data: MathData { text },
+            },
+            i + 1,
+        ))
+    }
+
+    /// Parses a raw `<details>` element into a toggle block. The `<summary>` is
+    /// the toggle title and the remaining content is parsed as Markdown.
+    fn parse_details(&self, start: usize) -> Option<(EditorJsBlock, usize)> {
+        let opening = DETAILS_OPEN.captures(&self.lines[start])?;
+        let open = opening.get(1).is_some_and(|attrs| {
+            attrs
+                .as_str()
+                .split_whitespace()
+                .any(|attr| attr.eq_ignore_ascii_case("open"))
+        });
+
+        let mut depth = 0;
+        let mut end = None;
+        for i in start..self.lines.len() {
+            for tag in DETAILS_TAG.captures_iter(&self.lines[i]) {
+                depth += if tag[1].is_empty() { 1 } else { -1 };
+            }
+            if depth <= 0 {
+                end = Some(i);
+                break;
+            }
+        }
+        let end = end?;
+
+        let html = self.lines[start..=end].join("\n");
+        let close = DETAILS_TAG
+            .find_iter(&html)
+            .last()
+            .filter(|tag| tag.as_str().starts_with("</"))?;
+        let content = &html[opening.get(0).unwrap().end()..close.start()];
+
+        let (title, body) = match SUMMARY.captures(content) {
+            Some(summary) => (
+                self.render_inline(summary[1].trim()),
+                &content[summary.get(0).unwrap().end()..],
+            ),
+            None => ("Details".to_string(), content),
+        };
+
+        let mut parser = self.nested_parser(body.lines().map(|line| line.to_string()).collect());
+        parser.nested = false;
+        let children = parser.parse().unwrap_or_default();
+
+        Some((
+            EditorJsBlock::Toggle {
+                data: ToggleData {
+                    text: title,
+                    status: if open { "open" } else { "closed" }.to_string(),
+                    fk: None,
+                    items: children.len(),
+                },
+                children,
+            },
+            end + 1,
+        ))
+    }
+
+    /// Index of the first definition line when the terms starting at `start`
+    /// (one per line, optionally followed by a blank line) are followed by a
+    /// `: Definition` line
+    fn definition_start(&self, start: usize) -> Option<usize> {
+        let mut i = start;
+        while i < self.lines.len()
+            && !self.lines[i].trim().is_empty()
+            && !DEFINITION_MARKER.is_match(&self.lines[i])
+        {
+            i += 1;
+        }
+        if i == start {
+            return None;
+        }
+        if self.lines.get(i).is_some_and(|line| line.trim().is_empty()) {
+            i += 1;
+        }
+        self.lines
+            .get(i)
+            .filter(|line| DEFINITION_MARKER.is_match(line))
+            .map(|_| i)
+    }
+
+    /// Parses a PHP Markdown Extra definition list. Each definition starts with
+    /// `: ` and continues on following lines; paragraphs after a blank line
+    /// belong to it when indented by four spaces.
+    fn parse_definition_list(&self, start: usize) -> Option<(EditorJsBlock, usize)> {
+        let mut items = Vec::new();
+        let mut i = start;
+
+        while let Some(mut j) = self.definition_start(i) {
+            let term = self.lines[i..j]
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| self.render_inline(line))
+                .collect::<Vec<_>>()
+                .join("<br>");
+
+            let mut definitions = Vec::new();
+            while let Some(cap) = self
+                .lines
+                .get(j)
+                .and_then(|l| DEFINITION_MARKER.captures(l))
+            {
+                let mut body = vec![cap[1].to_string()];
+                j += 1;
+
+                while j < self.lines.len() {
+                    let line = &self.lines[j];
+                    if line.trim().is_empty() {
+                        let next = self.lines[j..].iter().position(|l| !l.trim().is_empty());
+                        match next.map(|offset| &self.lines[j + offset]) {
+                            Some(next) if self.indent_width(next) >= 4 => {
+                                body.push(String::new());
+                                j += 1;
+                            }
+                            _ => break,
+                        }
+                    } else if DEFINITION_MARKER.is_match(line) || self.definition_start(j).is_some()
+                    {
+                        break;
+                    } else {
+                        body.push(self.strip_indent(line, 4));
+                        j += 1;
+                    }
+                }
+
+                definitions.push(self.block_text(body));
+
+                // Blank lines may separate definitions of the same term
+                let next = self.lines[j..].iter().position(|l| !l.trim().is_empty());
+                match next {
+                    Some(offset) if DEFINITION_MARKER.is_match(&self.lines[j + offset]) => {
+                        j += offset
+                    }
+                    _ => break,
+                }
+            }
+
+            items.push(DefinitionListItem { term, definitions });
+
+            i = j;
+            while self.lines.get(i).is_some_and(|line| line.trim().is_empty()) {
+                i += 1;
+            }
+        }
+
+        if items.is_empty() {
+            return None;
+        }
+        Some((definition_list_block(items, self.options), i))
+    }
+
+    /// Parses a code block indented by four spaces
+    fn parse_indented_code_block(&self, start: usize) -> (EditorJsBlock, usize) {
+        let mut code_lines = Vec::new();
+        let mut i = start;
+
+        while i < self.lines.len() {
+            let line = &self.lines[i];
+            if line.trim().is_empty() {
+                code_lines.push(String::new());
+            } else if self.indent_width(line) >= 4 {
+                code_lines.push(self.strip_indent(line, 4));
+            } else {
+                break;
+            }
+            i += 1;
+        }
+
+        // Trailing blank lines separate the block from what follows
+        while code_lines.last().is_some_and(|line| line.is_empty()) {
+            code_lines.pop();
         }
 
         let block = EditorJsBlock::Code {
             data: CodeData {
                 code: code_lines.join("\n"),
-                language: if language.is_empty() {
-                    None
-                } else {
-                    Some(language)
-                },
+                language: None,
             },
         };
 
-        (Some(block), if i < self.lines.len() { i + 1 } else { i })
+        (block, i)
     }
 
-    fn parse_blockquote(&self, start: usize) -> (EditorJsBlock, usize) {
-        let mut lines = Vec::new();
+    /// Parses a blockquote. Blank `>` lines separate paragraphs, a trailing
+    /// `— Author` line becomes the caption and nested quotes are emitted as
+    /// quote blocks of their own, since Editor.js quotes cannot be nested.
+    fn parse_blockquote(&self, start: usize) -> (Vec<EditorJsBlock>, usize) {
+        let mut body: Vec<String> = Vec::new();
         let mut i = start;
 
-        while i < self.lines.len() && self.lines[i].trim_start().starts_with("> ") {
-            let line = self.lines[i].trim_start();
-            lines.push(line[2..].trim().to_string());
+        while i < self.lines.len() {
+            let line = &self.lines[i];
+            if let Some(rest) = line.trim_start().strip_prefix('>') {
+                body.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+            } else if !line.trim().is_empty()
+                && body.last().is_some_and(|l| !l.trim().is_empty())
+                && !self.starts_block(i)
+            {
+                // Lazy continuation of the quoted paragraph
+                body.push(line.trim().to_string());
+            } else {
+                break;
+            }
             i += 1;
         }
 
-        let mut text = lines.join(" ");
-        text = self.convert_inline_formatting(&text);
-        text = self.convert_markdown_links(&text);
+        let mut caption = None;
+        if let Some(last) = body.iter().rposition(|line| !line.trim().is_empty()) {
+            let has_text = body[..last].iter().any(|line| !line.trim().is_empty());
+            if let Some(author) = quote_attribution(&body[last]).filter(|_| has_text) {
+                caption = Some(self.render_inline(author));
+                body.truncate(last);
+            }
+        }
 
-        (
-            EditorJsBlock::Quote {
-                data: QuoteData {
-                    text,
-                    caption: None,
-                    alignment: "left".to_string(),
-                },
+        let mut blocks = Vec::new();
+        let mut paragraphs = Vec::new();
+        for block in self.nested_parser(body).parse().unwrap_or_default() {
+            match block {
+                EditorJsBlock::Paragraph { data } => paragraphs.push(data.text),
+                EditorJsBlock::Quote { .. } => {
+                    if !paragraphs.is_empty() {
+                        blocks.push(quote_block(paragraphs.join("<br><br>"), None));
+                        paragraphs.clear();
+                    }
+                    blocks.push(block);
+                }
+                block => paragraphs.extend(inline_block_text(&block)),
+            }
+        }
+        if !paragraphs.is_empty() || blocks.is_empty() {
+            blocks.push(quote_block(paragraphs.join("<br><br>"), caption));
+        } else if let Some(EditorJsBlock::Quote { data }) = blocks.last_mut() {
+            data.caption = data.caption.take().or(caption);
+        }
+
+        (blocks, i)
+    }
+
+    /// Parses a GitHub alert: a blockquote starting with a `[!NOTE]`, `[!TIP]`,
+    /// `[!IMPORTANT]`, `[!WARNING]` or `[!CAUTION]` marker
+    fn parse_alert(&self, start: usize) -> Option<(EditorJsBlock, usize)> {
+        let marker = self.lines[start].trim_start().strip_prefix('>')?;
+        let cap = ALERT_MARKER.captures(marker.trim())?;
+        let title = match cap.get(2).map(|m| m.as_str().trim()) {
+            Some(title) if !title.is_empty() => self.render_inline(title),
+            _ => warning_title(&cap[1]),
+        };
+
+        let mut body = Vec::new();
+        let mut i = start + 1;
+        while i < self.lines.len() {
+            let Some(rest) = self.lines[i].trim_start().strip_prefix('>') else {
+                break;
+            };
+            body.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+            i += 1;
+        }
+
+        Some((self.warning_block(title, body), i))
+    }
+
+    /// Parses a Python-Markdown style admonition: a `!!! kind "Optional title"`
+    /// line followed by content indented by four spaces
+    fn parse_admonition(&self, start: usize) -> Option<(EditorJsBlock, usize)> {
+        let cap = ADMONITION.captures(self.lines[start].trim_end())?;
+        let title = match cap.get(2) {
+            Some(title) => self.render_inline(title.as_str().trim()),
+            None => warning_title(&cap[1]),
+        };
+
+        let mut body = Vec::new();
+        let mut i = start + 1;
+        while i < self.lines.len() {
+            let line = &self.lines[i];
+            if line.trim().is_empty() {
+                // Blank lines only belong to the admonition if indented content follows
+                let next = (i..self.lines.len()).find(|&j| !self.lines[j].trim().is_empty());
+                match next {
+                    Some(j) if self.indent_width(&self.lines[j]) >= 4 => {
+                        body.extend(std::iter::repeat_n(String::new(), j - i));
+                        i = j;
+                        continue;
+                    }
+                    _ => break,
+                }
+            }
+            if self.indent_width(line) < 4 {
+                break;
+            }
+            body.push(self.strip_indent(line, 4));
+            i += 1;
+        }
+
+        Some((self.warning_block(title, body), i))
+    }
+
+    /// Builds a warning block whose message is the text of the given Markdown
+    /// lines, with paragraphs and other blocks on separate lines
+    fn warning_block(&self, title: String, body: Vec<String>) -> EditorJsBlock {
+        EditorJsBlock::Warning {
+            data: WarningData {
+                title,
+                message: self.block_text(body),
             },
-            i,
-        )
+        }
+    }
+
+    /// Parses the raw HTML embed snippet of a Twitter, Instagram, TikTok or
+    /// Facebook post starting at `start`, dropping the `<script>` tags that
+    /// come with it. Text after the snippet on its last line becomes a
+    /// paragraph.
+    fn parse_embed_snippet(&self, start: usize) -> Option<(Vec<EditorJsBlock>, usize)> {
+        let first = self.lines[start].trim_start();
+        if !SNIPPET_START.is_match(first) {
+            return None;
+        }
+
+        let html = self.lines[start..].join("\n");
+        let (snippet, len) = parse_snippet_html(&html)?;
+        let embed = self.options.embeds.detect(&snippet.url)?;
+        let next = start + html[..len].matches('\n').count() + 1;
+
+        let mut blocks = vec![EditorJsBlock::Embed {
+            data: embed.into_data(snippet.caption),
+        }];
+        let rest = html[len..].split('\n').next().unwrap_or("").trim();
+        if !rest.is_empty() {
+            blocks.extend(paragraph_blocks(&self.render_inline(rest), self.options));
+        }
+
+        Some((blocks, next))
     }
 
     fn parse_image_markdown(&self, line: &str) -> Option<EditorJsBlock> {
@@ -388,6 +954,10 @@ impl MarkdownParser {
 
             let caption = title.or(alt_text);
 
+            if let Some(block) = media_block(&url, caption.clone()) {
+                return Some(block);
+            }
+
             return Some(EditorJsBlock::Image {
                 data: ImageData {
                     url,
@@ -408,21 +978,31 @@ impl MarkdownParser {
     fn parse_embed_link(&self, line: &str) -> Option<EditorJsBlock> {
         let trimmed = line.trim();
 
-        // Match standalone URL on its own line
-        let url_re = Regex::new(r"^https?://[^\s]+$").unwrap();
-        if url_re.is_match(trimmed) {
-            if let Some((service, embed_url, width, height)) = detect_embed_service(trimmed) {
+        // Match standalone URL on its own line, bare or as an autolink
+        let url_re = Regex::new(r"^<?(https?://[^\s<>]+)>?$").unwrap();
+        if let Some(cap) = url_re.captures(trimmed) {
+            let url = &cap[1];
+            if let Some(embed) = self.options.embeds.detect(url) {
                 return Some(EditorJsBlock::Embed {
-                    data: EmbedData {
-                        service,
-                        source: trimmed.to_string(),
-                        embed: embed_url,
-                        width,
-                        height,
-                        caption: None,
-                    },
+                    data: embed.into_data(None),
                 });
             }
+            if let Some(block) = media_block(url, None) {
+                return Some(block);
+            }
+            if self.options.standalone_links == StandaloneLinkStyle::LinkTool {
+                return Some(link_tool_block(url));
+            }
+            return None;
+        }
+
+        // A line holding only a link to a media file becomes a media block
+        let standalone_link_re = Regex::new(r#"^\[([^\]]*)\]\((https?://[^)\s]+)\)$"#).unwrap();
+        let standalone_link = standalone_link_re.captures(trimmed);
+        if let Some(cap) = &standalone_link {
+            if let Some(block) = media_block(&cap[2], Some(cap[1].to_string())) {
+                return Some(block);
+            }
         }
 
         // Match markdown link syntax: [text](url)
@@ -431,20 +1011,20 @@ impl MarkdownParser {
             let url = cap.get(2).map(|m| m.as_str()).unwrap_or("");
             let caption = cap.get(1).map(|m| m.as_str().to_string());
 
-            if let Some((service, embed_url, width, height)) = detect_embed_service(url) {
+            if let Some(embed) = self.options.embeds.detect(url) {
                 return Some(EditorJsBlock::Embed {
-                    data: EmbedData {
-                        service,
-                        source: url.to_string(),
-                        embed: embed_url,
-                        width,
-                        height,
-                        caption,
-                    },
+                    data: embed.into_data(caption),
                 });
             }
         }
 
+        // Other links alone on a line become link previews
+        if self.options.standalone_links == StandaloneLinkStyle::LinkTool {
+            if let Some(cap) = standalone_link {
+                return Some(link_tool_block(&cap[2]));
+            }
+        }
+
         None
     }
 
@@ -481,8 +1061,9 @@ impl MarkdownParser {
 
     fn parse_table_row(&self, line: &str) -> Vec<String> {
         line.split('|')
-            .map(|cell| cell.trim().to_string())
+            .map(|cell| cell.trim())
             .filter(|cell| !cell.is_empty())
+            .map(|cell| self.render_inline(cell))
             .collect()
     }
 
@@ -509,59 +1090,243 @@ impl MarkdownParser {
         let mut lines = Vec::new();
         let mut i = start;
 
-        while i < self.lines.len() && !self.lines[i].is_empty() {
-            lines.push(self.lines[i].clone());
+        while i < self.lines.len() && !self.lines[i].trim().is_empty() {
+            // Headings, fences, lists, quotes and rules interrupt a paragraph
+            if i > start && self.starts_block(i) {
+                break;
+            }
+            lines.push(self.lines[i].trim_start());
             i += 1;
         }
 
         // Skip empty lines
-        while i < self.lines.len() && self.lines[i].is_empty() {
+        while i < self.lines.len() && self.lines[i].trim().is_empty() {
             i += 1;
         }
 
-        let mut text = lines.join(" ");
-        text = self.convert_inline_formatting(&text);
-        text = self.convert_markdown_links(&text);
+        // Lines ending in two spaces or a backslash are hard line breaks
+        let mut text = String::new();
+        for (idx, line) in lines.iter().enumerate() {
+            if idx + 1 == lines.len() {
+                text.push_str(line.trim_end());
+            } else if line.ends_with("  ") || line.ends_with('\\') {
+                text.push_str(line.trim_end().trim_end_matches('\\').trim_end());
+                text.push_str("<br>");
+            } else {
+                text.push_str(line.trim_end());
+                text.push(' ');
+            }
+        }
+
+        text = self.render_inline(&text);
 
         (text, i)
     }
 
-    fn convert_inline_formatting(&self, text: &str) -> String {
-        let mut result = text.to_string();
+    fn is_horizontal_rule(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        (trimmed == "---" || trimmed == "***" || trimmed == "___") && trimmed.len() >= 3
+    }
+}
+
+/// Collects link reference definitions (`[label]: url "title"`) and blanks out
+/// their lines, so references resolve anywhere in the document. The first
+/// definition of a label wins; definitions inside code fences are ignored.
+fn extract_link_references(lines: &mut [String]) -> LinkReferences {
+    let mut references = LinkReferences::new();
+    let mut in_fence = false;
+    let mut after_break = true;
+
+    for line in lines.iter_mut() {
+        if code_fence(line).is_some() {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            after_break = false;
+            continue;
+        }
+
+        // Definitions cannot interrupt a paragraph
+        let definition = LINK_DEFINITION.captures(line).filter(|_| after_break);
+        if let Some(cap) = definition {
+            let url = cap.get(2).or(cap.get(3)).map_or("", |m| m.as_str());
+            let title = cap.get(4).or(cap.get(5)).or(cap.get(6));
+            references
+                .entry(normalize_label(&cap[1]))
+                .or_insert(LinkReference {
+                    url: url.to_string(),
+                    title: title.map(|m| m.as_str().to_string()),
+                });
+            line.clear();
+            continue;
+        }
 
-        // Convert strikethrough: ~~text~~ -> <s>text</s>
-        let strikethrough = Regex::new(r"~~([^~]+)~~").unwrap();
-        result = strikethrough.replace_all(&result, "<s>$1</s>").into_owned();
+        after_break = line.trim().is_empty();
+    }
 
-        // Convert bold: **text** -> <b>text</b>
-        let bold = Regex::new(r"\*\*([^\*]+)\*\*").unwrap();
-        result = bold.replace_all(&result, "<b>$1</b>").into_owned();
+    references
+}
+
+/// An opening code fence of three or more backticks or tildes
+struct CodeFence<'l> {
+    ch: char,
+    len: usize,
+    indent: usize,
+    info: &'l str,
+}
 
-        // Convert italic: _text_ -> <i>text</i> (but not in links or bold)
-        let italic = Regex::new(r"_([^_]+)_").unwrap();
-        result = italic.replace_all(&result, "<i>$1</i>").into_owned();
+impl CodeFence<'_> {
+    fn is_closed_by(&self, line: &str) -> bool {
+        code_fence(line).is_some_and(|closing| {
+            closing.ch == self.ch && closing.len >= self.len && closing.info.is_empty()
+        })
+    }
+}
+
+/// Parses a code fence line: up to three spaces of indentation, a run of at
+/// least three backticks or tildes and an optional info string
+fn code_fence(line: &str) -> Option<CodeFence<'_>> {
+    let rest = line.trim_start_matches(' ');
+    let indent = line.len() - rest.len();
+    let ch = rest.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = rest.chars().take_while(|&c| c == ch).count();
+
+    if indent > 3 || len < 3 {
+        return None;
+    }
+    let info = rest[len..].trim();
+    // Backtick fences cannot have backticks in their info string, so that
+    // inline code spans are not mistaken for fences
+    if ch == '`' && info.contains('`') {
+        return None;
+    }
+
+    Some(CodeFence {
+        ch,
+        len,
+        indent,
+        info,
+    })
+}
+
+fn quote_block(text: String, caption: Option<String>) -> EditorJsBlock {
+    EditorJsBlock::Quote {
+        data: QuoteData {
+            text,
+            caption,
+            alignment: "left".to_string(),
+        },
+    }
+}
+
+/// Collects footnote definitions (`[^label]: text`, continued by lines indented
+/// by four spaces) and blanks their lines so they are not parsed as content
+fn extract_footnote_definitions(lines: &mut [String]) -> FootnoteDefinitions {
+    let mut footnotes = FootnoteDefinitions::new();
+    let mut in_fence = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        if code_fence(&lines[i]).is_some() {
+            in_fence = !in_fence;
+        }
+        let Some(cap) = FOOTNOTE_DEFINITION
+            .captures(&lines[i])
+            .filter(|_| !in_fence)
+        else {
+            i += 1;
+            continue;
+        };
+
+        let key = footnote_key(&cap[1]);
+        let mut body = vec![cap[2].to_string()];
+        lines[i].clear();
+        i += 1;
+
+        while i < lines.len() {
+            if lines[i].trim().is_empty() {
+                // Blank lines only belong to the footnote if indented content follows
+                let next = (i..lines.len()).find(|&j| !lines[j].trim().is_empty());
+                match next {
+                    Some(j) if lines[j].starts_with("    ") || lines[j].starts_with('\t') => {
+                        body.extend(std::iter::repeat_n(String::new(), j - i));
+                        i = j;
+                        continue;
+                    }
+                    _ => break,
+                }
+            }
+            let Some(content) = lines[i]
+                .strip_prefix("    ")
+                .or_else(|| lines[i].strip_prefix('\t'))
+            else {
+                break;
+            };
+            body.push(content.to_string());
+            lines[i].clear();
+            i += 1;
+        }
 
-        result
+        footnotes.entry(key).or_insert_with(|| body.join("\n"));
     }
 
-    fn convert_markdown_links(&self, text: &str) -> String {
-        // Pattern: [link text](url)
-        let link_pattern = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap();
-        link_pattern
-            .replace_all(text, |caps: &regex::Captures| {
-                let link_text = &caps[1];
-                let url = &caps[2];
-                format!(r#"<a href="{}" target="_blank">{}</a>"#, url, link_text)
-            })
-            .into_owned()
-    }
+    footnotes
+}
 
-    fn is_horizontal_rule(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        (trimmed == "---" || trimmed == "***" || trimmed == "___") && trimmed.len() >= 3
+/// Text of a block kept inside a list item (when nested blocks are inlined) or a
+/// warning message
+fn inline_block_text(block: &EditorJsBlock) -> Option<String> {
+    match block {
+        EditorJsBlock::Heading { data } => Some(data.text.clone()),
+        EditorJsBlock::Quote { data } => Some(data.text.clone()),
+        EditorJsBlock::Warning { data } => {
+            Some(format!("<b>{}</b><br>{}", data.title, data.message))
+        }
+        EditorJsBlock::List { data } => Some(list_item_lines(&data.items).join("<br>")),
+        EditorJsBlock::Math { data } => Some(inline_math_html(&data.text)),
+        EditorJsBlock::DefinitionList { data } => Some(
+            data.items
+                .iter()
+                .flat_map(|item| std::iter::once(&item.term).chain(&item.definitions))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("<br>"),
+        ),
+        EditorJsBlock::Toggle { data, children } => Some(
+            std::iter::once(format!("<b>{}</b>", data.text))
+                .chain(children.iter().filter_map(|child| match child {
+                    EditorJsBlock::Paragraph { data } => Some(data.text.clone()),
+                    child => inline_block_text(child),
+                }))
+                .collect::<Vec<_>>()
+                .join("<br>"),
+        ),
+        EditorJsBlock::Code {
+            data: CodeData { code, .. },
+        }
+        | EditorJsBlock::Diagram {
+            data: DiagramData { code, .. },
+            ..
+        } => {
+            let code = code
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('\n', "<br>");
+            Some(format!(r#"<code class="inline-code">{}</code>"#, code))
+        }
+        _ => None,
     }
 }
 
+/// Contents of list items and their children, one item per line
+fn list_item_lines(items: &[ListItem]) -> Vec<String> {
+    items
+        .iter()
+        .flat_map(|item| std::iter::once(item.content.clone()).chain(list_item_lines(&item.items)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -641,6 +1406,127 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_checklist_strips_markers_from_content() {
+        let md = "- [x] Pack the map\n- [ ] Charge the GPS";
+        let blocks = markdown_to_editorjs(md).unwrap();
+
+        if let EditorJsBlock::List { data } = &blocks[0] {
+            assert_eq!(data.style, "checklist");
+            assert_eq!(data.items[0].content, "Pack the map");
+            assert_eq!(data.items[1].content, "Charge the GPS");
+            assert!(data.items.iter().all(|item| item.meta.checked.is_some()));
+        } else {
+            panic!("Expected list block");
+        }
+    }
+
+    #[test]
+    fn test_list_starting_with_link_is_not_checklist() {
+        let md = "- [Trail map](https://example.com/map)\n- [Weather](https://example.com/weather)";
+        let blocks = markdown_to_editorjs(md).unwrap();
+
+        if let EditorJsBlock::List { data } = &blocks[0] {
+            assert_eq!(data.style, "unordered");
+            assert_eq!(data.items[0].meta.checked, None);
+            assert!(data.items[0].content.starts_with("<a href"));
+        } else {
+            panic!("Expected list block");
+        }
+    }
+
+    #[test]
+    fn test_list_item_continuation_paragraphs() {
+        let md = "- First line\ncontinues lazily\n\n  Second paragraph\n- Next item";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 1);
+
+        if let EditorJsBlock::List { data } = &blocks[0] {
+            assert_eq!(data.items.len(), 2);
+            assert_eq!(
+                data.items[0].content,
+                "First line continues lazily<br>Second paragraph"
+            );
+            assert_eq!(data.items[1].content, "Next item");
+        } else {
+            panic!("Expected list block");
+        }
+    }
+
+    #[test]
+    fn test_list_item_block_children_are_hoisted() {
+        let md = "1. Install\n\n   ```sh\n   cargo build\n   ```\n\n   > Takes a while\n2. Run";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        if let EditorJsBlock::List { data } = &blocks[0] {
+            assert_eq!(data.items.len(), 2);
+            assert_eq!(data.items[0].content, "Install");
+            assert_eq!(data.items[1].content, "Run");
+        } else {
+            panic!("Expected list block");
+        }
+        if let EditorJsBlock::Code { data } = &blocks[1] {
+            assert_eq!(data.code, "cargo build");
+            assert_eq!(data.language.as_deref(), Some("sh"));
+        } else {
+            panic!("Expected code block");
+        }
+        assert!(matches!(blocks[2], EditorJsBlock::Quote { .. }));
+    }
+
+    #[test]
+    fn test_list_item_block_children_inline() {
+        let md = "- Install\n\n  ```\n  a < b\n  ```";
+        let options = ConversionOptions {
+            list_item_blocks: ListItemBlocks::Inline,
+            ..Default::default()
+        };
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        assert_eq!(blocks.len(), 1);
+
+        if let EditorJsBlock::List { data } = &blocks[0] {
+            assert_eq!(
+                data.items[0].content,
+                r#"Install<br><code class="inline-code">a &lt; b</code>"#
+            );
+        } else {
+            panic!("Expected list block");
+        }
+    }
+
+    #[test]
+    fn test_unindented_line_after_blank_ends_list() {
+        let md = "- Item\n\nParagraph";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert!(matches!(blocks[1], EditorJsBlock::Paragraph { .. }));
+    }
+
+    #[test]
+    fn test_paragraph_hard_line_breaks() {
+        let md = "Storgatan 1  \n123 45 Göteborg\\\nSweden\nEurope";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 1);
+
+        if let EditorJsBlock::Paragraph { data } = &blocks[0] {
+            assert_eq!(data.text, "Storgatan 1<br>123 45 Göteborg<br>Sweden Europe");
+        } else {
+            panic!("Expected paragraph block");
+        }
+    }
+
+    #[test]
+    fn test_split_paragraph_on_double_hard_break() {
+        let md = "Verse one\\\n\\\nVerse two";
+        let options = ConversionOptions {
+            split_paragraphs_on_double_br: true,
+            ..Default::default()
+        };
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        assert_eq!(blocks.len(), 2);
+    }
+
     #[test]
     fn test_list_with_formatting() {
         let md = "- **Bold** item\n- _Italic_ item\n- ~~Strikethrough~~ item";
@@ -801,6 +1687,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reference_link_definitions_resolve_across_document() {
+        let md = "Read the [guide][1] and [Trails].\n\n[1]: https://example.com/guide \"Guide\"\n[trails]: <https://example.com/trails>";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 1);
+
+        if let EditorJsBlock::Paragraph { data } = &blocks[0] {
+            assert_eq!(
+                data.text,
+                r#"Read the <a href="https://example.com/guide" title="Guide" target="_blank">guide</a> and <a href="https://example.com/trails" target="_blank">Trails</a>."#
+            );
+        } else {
+            panic!("Expected paragraph block");
+        }
+    }
+
     // Inline Formatting Tests
     #[test]
     fn test_inline_bold_in_paragraph() {
@@ -930,4 +1832,450 @@ mod tests {
             panic!("Expected paragraph block");
         }
     }
+
+    #[test]
+    fn test_front_matter_is_not_converted_to_blocks() {
+        let md = "---\ntitle: Trail guide\nauthor: Anna\n---\n\n# Trail guide\n\nIntro";
+        let (blocks, meta) =
+            markdown_to_editorjs_with_front_matter(md, &ConversionOptions::default()).unwrap();
+
+        assert_eq!(blocks.len(), 2);
+        assert!(matches!(blocks[0], EditorJsBlock::Heading { .. }));
+        assert_eq!(meta["title"], "Trail guide");
+        assert_eq!(meta["author"], "Anna");
+
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_github_alert_becomes_warning() {
+        let md =
+            "> [!WARNING]\n> Trails are **closed** in winter.\n>\n> Check the forecast.\n\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        if let EditorJsBlock::Warning { data } = &blocks[0] {
+            assert_eq!(data.title, "Warning");
+            assert_eq!(
+                data.message,
+                "Trails are <b>closed</b> in winter.<br>Check the forecast."
+            );
+        } else {
+            panic!("Expected warning block");
+        }
+    }
+
+    #[test]
+    fn test_admonition_becomes_warning() {
+        let md =
+            "!!! note \"Good to know\"\n    Bring a map.\n\n    - Compass\n    - Headlamp\n\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        if let EditorJsBlock::Warning { data } = &blocks[0] {
+            assert_eq!(data.title, "Good to know");
+            assert_eq!(data.message, "Bring a map.<br>Compass<br>Headlamp");
+        } else {
+            panic!("Expected warning block");
+        }
+
+        let blocks = markdown_to_editorjs("!!! tip\n    Start early.").unwrap();
+        if let EditorJsBlock::Warning { data } = &blocks[0] {
+            assert_eq!(data.title, "Tip");
+            assert_eq!(data.message, "Start early.");
+        } else {
+            panic!("Expected warning block");
+        }
+    }
+
+    #[test]
+    fn test_footnotes_as_trailing_list() {
+        let md = "Long trail[^1] and remote.[^note]\n\nAgain[^1], and [^missing].\n\n[^1]: About 440 km.\n[^note]: See the **guide**.\n\n    Second paragraph.";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        if let EditorJsBlock::Paragraph { data } = &blocks[1] {
+            assert!(data
+                .text
+                .contains(r##"<sup><a href="#fn-1" id="fnref-1">1</a></sup>"##));
+            assert!(data.text.contains("[^missing]"));
+        } else {
+            panic!("Expected paragraph block");
+        }
+
+        if let EditorJsBlock::List { data } = &blocks[2] {
+            assert_eq!(data.style, "ordered");
+            assert_eq!(
+                data.items[0].content,
+                r##"About 440 km. <a href="#fnref-1">↩</a>"##
+            );
+            assert_eq!(
+                data.items[1].content,
+                r##"See the <b>guide</b>.<br>Second paragraph. <a href="#fnref-2">↩</a>"##
+            );
+        } else {
+            panic!("Expected list block");
+        }
+    }
+
+    #[test]
+    fn test_footnotes_as_block_tunes() {
+        let options = ConversionOptions {
+            footnotes: crate::options::FootnoteStyle::Tune,
+            ..Default::default()
+        };
+        let md = "Long trail.[^1]\n\nNo notes here.\n\n[^1]: About 440 km.";
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        let block = blocks[0].clone().with_ids().remove(0);
+        assert_eq!(block.block_type, "paragraph");
+        let footnotes = block.tunes.and_then(|tunes| tunes.footnotes).unwrap();
+        assert_eq!(footnotes.len(), 1);
+        assert_eq!(footnotes[0].id, "fn-1");
+        assert_eq!(footnotes[0].content, "About 440 km.");
+        assert_eq!(footnotes[0].superscript, 1);
+
+        let json = serde_json::to_value(&blocks[0]).unwrap();
+        assert_eq!(json["type"], "paragraph");
+        assert_eq!(
+            json["data"]["text"],
+            r#"Long trail.<sup data-tune="footnotes" data-id="fn-1">1</sup>"#
+        );
+        assert_eq!(json["tunes"]["footnotes"][0]["content"], "About 440 km.");
+        assert!(blocks[1].clone().with_ids()[0].tunes.is_none());
+    }
+
+    #[test]
+    fn test_multi_paragraph_blockquote_with_attribution() {
+        let md = ">First line\ncontinues\n>\n> Second paragraph\n>\n> — Ada Lovelace";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 1);
+
+        if let EditorJsBlock::Quote { data } = &blocks[0] {
+            assert_eq!(data.text, "First line continues<br><br>Second paragraph");
+            assert_eq!(data.caption.as_deref(), Some("Ada Lovelace"));
+        } else {
+            panic!("Expected quote block");
+        }
+    }
+
+    #[test]
+    fn test_nested_blockquote_becomes_separate_quote() {
+        let md = "> Outer\n>\n> > Inner\n\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        let texts: Vec<&str> = blocks[..2]
+            .iter()
+            .map(|block| match block {
+                EditorJsBlock::Quote { data } => data.text.as_str(),
+                _ => panic!("Expected quote block"),
+            })
+            .collect();
+        assert_eq!(texts, ["Outer", "Inner"]);
+    }
+
+    #[test]
+    fn test_tilde_fence_with_info_string() {
+        let md = "~~~~js title=\"main.js\"\nconst a = 1;\n~~~\n```\n~~~~~\n\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        if let EditorJsBlock::Code { data } = &blocks[0] {
+            // A shorter fence or a fence of the other character does not close the block
+            assert_eq!(data.code, "const a = 1;\n~~~\n```");
+            assert_eq!(data.language.as_deref(), Some("javascript"));
+        } else {
+            panic!("Expected code block");
+        }
+    }
+
+    #[test]
+    fn test_indented_code_block() {
+        let md = "Intro\n\n    fn main() {\n\n        run();\n    }\n\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        if let EditorJsBlock::Code { data } = &blocks[1] {
+            assert_eq!(data.code, "fn main() {\n\n    run();\n}");
+            assert_eq!(data.language, None);
+        } else {
+            panic!("Expected code block");
+        }
+    }
+
+    #[test]
+    fn test_display_math_blocks() {
+        let md = "$$\n\\int_0^1 x\\,dx\n$$\n\n```math\na^2+b^2=c^2\n```\n\n$$ x = 1 $$";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        let texts: Vec<&str> = blocks
+            .iter()
+            .map(|block| match block {
+                EditorJsBlock::Math { data } => data.text.as_str(),
+                _ => panic!("Expected math block"),
+            })
+            .collect();
+        assert_eq!(texts, [r"\int_0^1 x\,dx", "a^2+b^2=c^2", "x = 1"]);
+    }
+
+    #[test]
+    fn test_unclosed_display_math_does_not_interrupt_paragraph() {
+        let blocks = markdown_to_editorjs("Paragraph\n$$ not math\nmore").unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert!(
+            matches!(&blocks[0], EditorJsBlock::Paragraph { data } if data.text == "Paragraph $$ not math more")
+        );
+
+        let blocks = markdown_to_editorjs("Text\n$$\nx\n$$").unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert!(matches!(&blocks[1], EditorJsBlock::Math { data } if data.text == "x"));
+    }
+
+    #[test]
+    fn test_inline_math_is_not_emphasized() {
+        let md = "Sum $a_1 + b_2$ costs $5 and $10.";
+        let blocks = markdown_to_editorjs(md).unwrap();
+
+        if let EditorJsBlock::Paragraph { data } = &blocks[0] {
+            assert_eq!(
+                data.text,
+                r#"Sum <span class="math inline">a_1 + b_2</span> costs $5 and $10."#
+            );
+        } else {
+            panic!("Expected paragraph block");
+        }
+    }
+
+    #[test]
+    fn test_configured_fence_languages_become_diagrams() {
+        let options = ConversionOptions {
+            diagram_blocks: [
+                ("mermaid".to_string(), "diagram".to_string()),
+                ("plantuml".to_string(), "plantuml".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let md = "```mermaid\ngraph TD\n  A-->B\n```\n\n```plantuml\n@startuml\n```\n\n```graphviz\ndigraph {}\n```";
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        if let EditorJsBlock::Diagram { block_type, data } = &blocks[0] {
+            assert_eq!(block_type, "diagram");
+            assert_eq!(data.language, "mermaid");
+            assert_eq!(data.code, "graph TD\n  A-->B");
+        } else {
+            panic!("Expected diagram block");
+        }
+        assert!(
+            matches!(&blocks[1], EditorJsBlock::Diagram { block_type, .. } if block_type == "plantuml")
+        );
+        // Languages that are not configured stay code blocks
+        assert!(matches!(blocks[2], EditorJsBlock::Code { .. }));
+
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert!(blocks
+            .iter()
+            .all(|block| matches!(block, EditorJsBlock::Code { .. })));
+    }
+
+    #[test]
+    fn test_details_becomes_toggle() {
+        let md = "<details open>\n<summary>Show **more**</summary>\n\nBody with *emphasis*.\n\n- Item\n\n</details>\n\n<details><summary>Closed</summary>Text</details>\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        if let EditorJsBlock::Toggle { data, children } = &blocks[0] {
+            assert_eq!(data.text, "Show <b>more</b>");
+            assert_eq!(data.status, "open");
+            assert_eq!(data.items, 2);
+            assert!(
+                matches!(&children[0], EditorJsBlock::Paragraph { data } if data.text == "Body with <i>emphasis</i>.")
+            );
+            assert!(matches!(children[1], EditorJsBlock::List { .. }));
+        } else {
+            panic!("Expected toggle block");
+        }
+        assert!(
+            matches!(&blocks[1], EditorJsBlock::Toggle { data, .. } if data.status == "closed")
+        );
+        assert!(matches!(&blocks[2], EditorJsBlock::Paragraph { data } if data.text == "After"));
+    }
+
+    #[test]
+    fn test_definition_list() {
+        let md = "Apple\n: A **fruit**\n: A company\n\nOrange\nMandarin\n\n:   Citrus fruit\n    continued here\n\n    Second paragraph\n\nAfter";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        if let EditorJsBlock::List { data } = &blocks[0] {
+            assert_eq!(data.items.len(), 2);
+            assert_eq!(data.items[0].content, "Apple");
+            assert_eq!(data.items[0].items[0].content, "A <b>fruit</b>");
+            assert_eq!(data.items[0].items[1].content, "A company");
+            assert_eq!(data.items[1].content, "Orange<br>Mandarin");
+            assert_eq!(
+                data.items[1].items[0].content,
+                "Citrus fruit continued here<br>Second paragraph"
+            );
+        } else {
+            panic!("Expected list block");
+        }
+        assert!(matches!(&blocks[1], EditorJsBlock::Paragraph { data } if data.text == "After"));
+
+        let options = ConversionOptions {
+            definition_lists: crate::options::DefinitionListStyle::Block,
+            ..Default::default()
+        };
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        if let EditorJsBlock::DefinitionList { data } = &blocks[0] {
+            assert_eq!(data.items[0].term, "Apple");
+            assert_eq!(data.items[0].definitions, ["A <b>fruit</b>", "A company"]);
+        } else {
+            panic!("Expected definition list block");
+        }
+    }
+
+    #[test]
+    fn test_media_links() {
+        let md = "https://cdn.example.com/video/summit.mp4\n\n[Bird song](https://cdn.example.com/birds.MP3?v=2)\n\n![Timelapse](/video/timelapse.webm)\n\nSee [the clip](https://cdn.example.com/clip.mp4) first.";
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 4);
+
+        let media: Vec<_> = blocks[..3]
+            .iter()
+            .map(|block| match block {
+                EditorJsBlock::Media { data } => (
+                    data.kind,
+                    data.mime_type.as_deref().unwrap(),
+                    data.caption.as_deref(),
+                ),
+                other => panic!("Expected media block, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            media,
+            vec![
+                (MediaKind::Video, "video/mp4", None),
+                (MediaKind::Audio, "audio/mpeg", Some("Bird song")),
+                (MediaKind::Video, "video/webm", Some("Timelapse")),
+            ]
+        );
+        assert!(matches!(blocks[3], EditorJsBlock::Paragraph { .. }));
+    }
+
+    #[test]
+    fn test_standalone_links_as_link_tool() {
+        let md = "https://example.com/guide\n\n<https://example.com/map>\n\n[Packing list](https://example.com/packing)\n\nSee https://example.com/ for more.\n\nhttps://youtu.be/dQw4w9WgXcQ";
+
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert!(blocks[..4]
+            .iter()
+            .all(|block| matches!(block, EditorJsBlock::Paragraph { .. })));
+
+        let options = ConversionOptions {
+            standalone_links: crate::options::StandaloneLinkStyle::LinkTool,
+            ..Default::default()
+        };
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        let links: Vec<_> = blocks
+            .iter()
+            .filter_map(|block| match block {
+                EditorJsBlock::LinkTool { data } => Some(data.link.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/guide",
+                "https://example.com/map",
+                "https://example.com/packing"
+            ]
+        );
+        assert!(matches!(blocks[3], EditorJsBlock::Paragraph { .. }));
+        assert!(matches!(blocks[4], EditorJsBlock::Embed { .. }));
+    }
+
+    #[test]
+    fn test_embed_snippets() {
+        let md = r#"Before
+
+<blockquote class="twitter-tweet"><p lang="en" dir="ltr">Sunrise over Kebnekaise</p>&mdash; Trail Runner (@trailrunner) <a href="https://twitter.com/trailrunner/status/1234567890123?ref_src=twsrc%5Etfw">June 1, 2024</a></blockquote>
+<script async src="https://platform.twitter.com/widgets.js" charset="utf-8"></script>
+
+<blockquote class="tiktok-embed" cite="https://www.tiktok.com/@hiker/video/7301234567890" data-video-id="7301234567890">
+<section><a title="@hiker" href="https://www.tiktok.com/@hiker">@hiker</a> Crossing the pass</section>
+</blockquote> <script async src="https://www.tiktok.com/embed.js"></script>
+After"#;
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 4);
+
+        let embeds: Vec<_> = blocks[1..3]
+            .iter()
+            .map(|block| match block {
+                EditorJsBlock::Embed { data } => (data.service.as_str(), data.caption.as_deref()),
+                other => panic!("Expected embed block, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            embeds,
+            vec![
+                ("twitter", Some("Sunrise over Kebnekaise")),
+                ("tiktok", Some("Crossing the pass")),
+            ]
+        );
+        assert!(matches!(&blocks[3], EditorJsBlock::Paragraph { data } if data.text == "After"));
+    }
+
+    #[test]
+    fn test_text_after_embed_snippet_is_kept() {
+        let md = r#"<blockquote class="twitter-tweet"><p>Sunrise over Kebnekaise</p><a href="https://twitter.com/trailrunner/status/1234567890123">June 1, 2024</a></blockquote> Important **trailing** note
+Next line"#;
+        let blocks = markdown_to_editorjs(md).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        assert!(matches!(&blocks[0], EditorJsBlock::Embed { data } if data.service == "twitter"));
+        assert!(
+            matches!(&blocks[1], EditorJsBlock::Paragraph { data } if data.text == "Important <b>trailing</b> note")
+        );
+        assert!(
+            matches!(&blocks[2], EditorJsBlock::Paragraph { data } if data.text == "Next line")
+        );
+    }
+
+    #[test]
+    fn test_custom_embed_service_from_options() {
+        let mut embeds = crate::embed::EmbedRegistry::default();
+        embeds.register(
+            crate::embed::EmbedService::new(
+                "trailmap",
+                r"maps\.example\.com/t/(\w+)",
+                "https://maps.example.com/embed/{}",
+                800,
+                600,
+            )
+            .unwrap(),
+        );
+        let options = ConversionOptions {
+            embeds,
+            ..Default::default()
+        };
+
+        let md = "[Kungsleden](https://maps.example.com/t/kungsleden)";
+        let blocks = markdown_to_editorjs_with_options(md, &options).unwrap();
+        if let EditorJsBlock::Embed { data } = &blocks[0] {
+            assert_eq!(data.service, "trailmap");
+            assert_eq!(data.embed, "https://maps.example.com/embed/kungsleden");
+            assert_eq!(data.caption.as_deref(), Some("Kungsleden"));
+        } else {
+            panic!("Expected embed block");
+        }
+        assert!(matches!(
+            markdown_to_editorjs(md).unwrap()[0],
+            EditorJsBlock::Paragraph { .. }
+        ));
+    }
 }
@@ -0,0 +1,914 @@
+//! Inline Markdown parser producing Editor.js inline markup
+//!
+//! Handles code spans, emphasis (`*`/`_`), strong emphasis (`**`/`__`),
+//! strikethrough (`~~`), highlights (`==`), links (inline, reference,
+//! autolinks and bare URLs), footnote references, inline math (`$x$`),
+//! backslash escapes and raw inline HTML. Emphasis
+//! is resolved with the CommonMark delimiter algorithm, so precedence and
+//! intraword underscores behave as in CommonMark.
+
+use crate::footnotes::{reference_html, FootnoteDefinitions};
+use crate::models::inline_math_html;
+use crate::options::{ConversionOptions, LinkTarget};
+use regex::Regex;
+use std::collections::HashMap;
+
+lazy_static::lazy_static! {
+    static ref INLINE_HTML_TAG: Regex = Regex::new(r#"^</?[A-Za-z][A-Za-z0-9-]*(?:\s[^<>]*)?/?>"#).unwrap();
+    static ref URI_AUTOLINK: Regex = Regex::new(r"^<([A-Za-z][A-Za-z0-9+.-]{1,31}:[^\s<>]*)>").unwrap();
+    static ref EMAIL_AUTOLINK: Regex = Regex::new(r"^<([A-Za-z0-9.!#$%&'*+/=?^_`{|}~-]+@[A-Za-z0-9](?:[A-Za-z0-9-]{0,61}[A-Za-z0-9])?(?:\.[A-Za-z0-9](?:[A-Za-z0-9-]{0,61}[A-Za-z0-9])?)*)>").unwrap();
+    static ref BARE_URL: Regex = Regex::new(r"^(?:https?://|www\.)[^\s<]*").unwrap();
+}
+
+/// A link reference definition (`[label]: url "title"`)
+#[derive(Debug, Clone)]
+pub(crate) struct LinkReference {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Link reference definitions of a document, keyed by normalized label
+pub(crate) type LinkReferences = HashMap<String, LinkReference>;
+
+/// Normalizes a link label for matching: case-insensitive, with runs of
+/// whitespace collapsed to a single space
+pub(crate) fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Key of the footnote with the given label, used as the target of its references
+pub(crate) fn footnote_key(label: &str) -> String {
+    format!("fn-{}", normalize_label(label))
+}
+
+/// Renders inline Markdown as Editor.js inline HTML
+pub(crate) struct InlineRenderer<'a> {
+    references: &'a LinkReferences,
+    footnotes: Option<&'a FootnoteDefinitions>,
+    link_target: LinkTarget,
+}
+
+impl<'a> InlineRenderer<'a> {
+    pub(crate) fn new(references: &'a LinkReferences, options: &ConversionOptions) -> Self {
+        InlineRenderer {
+            references,
+            footnotes: None,
+            link_target: options.link_target,
+        }
+    }
+
+    /// Renders `[^label]` as a reference to the footnote when it is defined
+    pub(crate) fn with_footnotes(mut self, footnotes: &'a FootnoteDefinitions) -> Self {
+        self.footnotes = Some(footnotes);
+        self
+    }
+
+    pub(crate) fn render(&self, text: &str) -> String {
+        InlineParser::new(text, self, true).render()
+    }
+
+    fn link_html(&self, url: &str, title: Option<&str>, content: &str) -> String {
+        let mut html = format!(r#"<a href="{}""#, escape_url(url));
+        if let Some(title) = title {
+            html.push_str(&format!(r#" title="{}""#, escape_attribute(title)));
+        }
+        let external =
+            url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//");
+        let blank = match self.link_target {
+            LinkTarget::Always => true,
+            LinkTarget::External => external,
+            LinkTarget::Never => false,
+        };
+        if blank {
+            html.push_str(r#" target="_blank""#);
+        }
+        html.push('>');
+        html.push_str(content);
+        html.push_str("</a>");
+        html
+    }
+}
+
+/// A parsed piece of inline content
+enum Node {
+    /// Final HTML output
+    Html(String),
+    /// A run of emphasis delimiter characters
+    Delim(Delimiter),
+}
+
+struct Delimiter {
+    ch: char,
+    /// Number of delimiter characters not yet used for emphasis
+    count: usize,
+    /// Length of the run as written, for the CommonMark "rule of three"
+    original_count: usize,
+    can_open: bool,
+    can_close: bool,
+    active: bool,
+    /// Tags opened by this run, outermost first
+    open_tags: Vec<&'static str>,
+    /// Tags closed by this run, innermost first
+    close_tags: Vec<&'static str>,
+}
+
+struct InlineParser<'r> {
+    text: String,
+    chars: Vec<char>,
+    /// Byte offset in `text` of each char, and of the end of the text
+    offsets: Vec<usize>,
+    pos: usize,
+    nodes: Vec<Node>,
+    renderer: &'r InlineRenderer<'r>,
+    /// Whether bare URLs are turned into links (not inside link text)
+    autolink: bool,
+}
+
+impl<'r> InlineParser<'r> {
+    fn new(text: &str, renderer: &'r InlineRenderer<'r>, autolink: bool) -> Self {
+        InlineParser {
+            text: text.to_string(),
+            chars: text.chars().collect(),
+            offsets: text
+                .char_indices()
+                .map(|(offset, _)| offset)
+                .chain([text.len()])
+                .collect(),
+            pos: 0,
+            nodes: Vec::new(),
+            renderer,
+            autolink,
+        }
+    }
+
+    fn render(mut self) -> String {
+        self.tokenize();
+        self.process_emphasis();
+
+        let mut result = String::new();
+        for node in &self.nodes {
+            match node {
+                Node::Html(html) => result.push_str(html),
+                Node::Delim(delim) => {
+                    for tag in &delim.close_tags {
+                        result.push_str(&format!("</{}>", tag));
+                    }
+                    result.extend(std::iter::repeat_n(delim.ch, delim.count));
+                    for tag in &delim.open_tags {
+                        result.push_str(&format!("<{}>", tag));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(Node::Html(last)) = self.nodes.last_mut() {
+            last.push_str(text);
+        } else {
+            self.nodes.push(Node::Html(text.to_string()));
+        }
+    }
+
+    fn tokenize(&mut self) {
+        while self.pos < self.chars.len() {
+            let c = self.chars[self.pos];
+            match c {
+                '\\' => self.parse_escape(),
+                '`' => self.parse_code_span(),
+                '$' => self.parse_math_span(),
+                '[' if self.peek(1) == Some('^') => self.parse_footnote_reference(),
+                '[' => self.parse_link(1),
+                '!' if self.peek(1) == Some('[') => self.parse_link(2),
+                '<' => self.parse_inline_html(),
+                '*' | '_' | '~' | '=' => self.parse_delimiter_run(c),
+                'h' | 'w' if self.autolink && self.at_autolink_boundary() => {
+                    if !self.parse_bare_url() {
+                        self.push_text(&c.to_string());
+                        self.pos += 1;
+                    }
+                }
+                _ => {
+                    self.push_text(&c.to_string());
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn parse_escape(&mut self) {
+        match self.peek(1) {
+            Some(next) if next.is_ascii_punctuation() => {
+                self.push_text(&escape_html(&next.to_string()));
+                self.pos += 2;
+            }
+            _ => {
+                self.push_text("\\");
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn parse_code_span(&mut self) {
+        let run = self.run_length(self.pos, '`');
+        let content_start = self.pos + run;
+
+        // The span ends at the next backtick run of exactly the same length
+        let mut i = content_start;
+        while i < self.chars.len() {
+            if self.chars[i] == '`' {
+                let closing_run = self.run_length(i, '`');
+                if closing_run == run {
+                    let content: String = self.chars[content_start..i].iter().collect();
+                    let content = content.replace('\n', " ");
+                    let content = if content.len() > 2
+                        && content.starts_with(' ')
+                        && content.ends_with(' ')
+                        && !content.trim().is_empty()
+                    {
+                        &content[1..content.len() - 1]
+                    } else {
+                        content.as_str()
+                    };
+                    let html = format!(
+                        r#"<code class="inline-code">{}</code>"#,
+                        escape_html(content)
+                    );
+                    self.push_text(&html);
+                    self.pos = i + run;
+                    return;
+                }
+                i += closing_run;
+            } else {
+                i += 1;
+            }
+        }
+
+        // No closing run: the backticks are literal text
+        self.push_text(&"`".repeat(run));
+        self.pos = content_start;
+    }
+
+    /// Parses `$tex$` (or `$$tex$$`) inline math. As in Pandoc, the opening `$`
+    /// must not be followed by whitespace and the closing `$` must not be
+    /// preceded by whitespace or followed by a digit, so prices stay text.
+    fn parse_math_span(&mut self) {
+        let run = self.run_length(self.pos, '$').min(2);
+        let content_start = self.pos + run;
+
+        let opens = self
+            .chars
+            .get(content_start)
+            .is_some_and(|c| !c.is_whitespace() && *c != '$');
+        let mut i = content_start;
+        while opens && i < self.chars.len() {
+            match self.chars[i] {
+                '\\' => i += 2,
+                '$' if self.run_length(i, '$') >= run => {
+                    let closes = !self.chars[i - 1].is_whitespace()
+                        && !self.chars.get(i + run).is_some_and(|c| c.is_ascii_digit());
+                    if !closes {
+                        break;
+                    }
+                    let tex: String = self.chars[content_start..i].iter().collect();
+                    self.push_text(&inline_math_html(&tex));
+                    self.pos = i + run;
+                    return;
+                }
+                _ => i += 1,
+            }
+        }
+
+        self.push_text(&"$".repeat(run));
+        self.pos = content_start;
+    }
+
+    /// Parses a link starting at `[` (or an image at `![` when `marker_len` is
+    /// 2): inline `[text](url "title")`, full `[text][ref]`, collapsed
+    /// `[text][]` and shortcut `[ref]` reference links
+    fn parse_link(&mut self, marker_len: usize) {
+        let text_start = self.pos + marker_len;
+        let Some(text_end) = self.find_closing_bracket(text_start) else {
+            self.push_literal(marker_len);
+            return;
+        };
+        let text: String = self.chars[text_start..text_end].iter().collect();
+
+        let target = match self.chars.get(text_end + 1) {
+            Some('(') => self
+                .parse_destination(text_end + 2)
+                .map(|(url, title, end)| (url, title, end + 1)),
+            Some('[') => self
+                .find_closing_bracket(text_end + 2)
+                .and_then(|label_end| {
+                    let label: String = self.chars[text_end + 2..label_end].iter().collect();
+                    let label = if label.trim().is_empty() {
+                        &text
+                    } else {
+                        &label
+                    };
+                    self.lookup_reference(label)
+                        .map(|(url, title)| (url, title, label_end + 1))
+                }),
+            _ => self
+                .lookup_reference(&text)
+                .map(|(url, title)| (url, title, text_end + 1)),
+        };
+
+        let Some((url, title, end)) = target else {
+            self.push_literal(marker_len);
+            return;
+        };
+
+        // Images inside text cannot be shown by Editor.js text blocks, so they
+        // are linked with their alt text instead
+        let content = InlineParser::new(&text, self.renderer, false).render();
+        let html = self.renderer.link_html(&url, title.as_deref(), &content);
+        self.push_text(&html);
+        self.pos = end;
+    }
+
+    /// Parses a `[^label]` footnote reference, falling back to a link when the
+    /// footnote is not defined
+    fn parse_footnote_reference(&mut self) {
+        let label_start = self.pos + 2;
+        let label_end = (label_start..self.chars.len()).find(|&i| {
+            let c = self.chars[i];
+            c == ']' || c.is_whitespace() || matches!(c, '[' | '"' | '\'' | '<' | '>')
+        });
+
+        let defined = label_end
+            .filter(|&end| end > label_start && self.chars[end] == ']')
+            .map(|end| (self.chars[label_start..end].iter().collect::<String>(), end))
+            .filter(|(label, _)| {
+                self.renderer
+                    .footnotes
+                    .is_some_and(|footnotes| footnotes.contains_key(&footnote_key(label)))
+            });
+
+        match defined {
+            Some((label, end)) => {
+                let html = reference_html(&footnote_key(&label), &escape_html(&label));
+                self.push_text(&html);
+                self.pos = end + 1;
+            }
+            None => self.parse_link(1),
+        }
+    }
+
+    fn lookup_reference(&self, label: &str) -> Option<(String, Option<String>)> {
+        if label.starts_with('^') {
+            return None;
+        }
+        self.renderer
+            .references
+            .get(&normalize_label(label))
+            .map(|reference| (reference.url.clone(), reference.title.clone()))
+    }
+
+    /// Parses a link destination and optional title following `(`, returning
+    /// them with the offset of the closing `)`
+    fn parse_destination(&self, from: usize) -> Option<(String, Option<String>, usize)> {
+        let mut i = self.skip_whitespace(from);
+        let mut url = String::new();
+
+        if self.chars.get(i) == Some(&'<') {
+            i += 1;
+            while i < self.chars.len() && self.chars[i] != '>' {
+                if self.chars[i] == '\n' {
+                    return None;
+                }
+                i += self.push_unescaped(&mut url, i);
+            }
+            i += 1;
+        } else {
+            let mut depth = 0;
+            while i < self.chars.len() {
+                match self.chars[i] {
+                    c if c.is_whitespace() => break,
+                    '(' => depth += 1,
+                    ')' if depth == 0 => break,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                i += self.push_unescaped(&mut url, i);
+            }
+        }
+
+        let after_url = i;
+        i = self.skip_whitespace(i);
+        let mut title = None;
+        if let Some(open @ ('"' | '\'' | '(')) = self.chars.get(i).copied() {
+            if i == after_url {
+                return None;
+            }
+            let close = if open == '(' { ')' } else { open };
+            let mut text = String::new();
+            i += 1;
+            while i < self.chars.len() && self.chars[i] != close {
+                i += self.push_unescaped(&mut text, i);
+            }
+            i += 1;
+            title = Some(text);
+            i = self.skip_whitespace(i);
+        }
+
+        (self.chars.get(i) == Some(&')')).then_some((url, title, i))
+    }
+
+    /// Appends the character at `i` to `out`, resolving a backslash escape,
+    /// and returns the number of characters consumed
+    fn push_unescaped(&self, out: &mut String, i: usize) -> usize {
+        match (self.chars[i], self.chars.get(i + 1)) {
+            ('\\', Some(&next)) if next.is_ascii_punctuation() => {
+                out.push(next);
+                2
+            }
+            (c, _) => {
+                out.push(c);
+                1
+            }
+        }
+    }
+
+    fn skip_whitespace(&self, mut i: usize) -> usize {
+        while i < self.chars.len() && self.chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    }
+
+    /// The text from the current position on
+    fn rest(&self) -> &str {
+        &self.text[self.offsets[self.pos]..]
+    }
+
+    /// Bare URLs are only recognized at the start of a word
+    fn at_autolink_boundary(&self) -> bool {
+        self.pos == 0
+            || matches!(self.chars[self.pos - 1], c if c.is_whitespace() || "*_~(".contains(c))
+    }
+
+    /// Links a bare `http(s)://` or `www.` URL (GFM extended autolinks)
+    fn parse_bare_url(&mut self) -> bool {
+        let Some(m) = BARE_URL.find(self.rest()) else {
+            return false;
+        };
+        let mut url = m.as_str();
+
+        // Trailing punctuation and unbalanced closing parentheses are not part of the URL
+        loop {
+            let trimmed = url.trim_end_matches(['?', '!', '.', ',', ':', '*', '_', '~', '\'', '"']);
+            let trimmed = if trimmed.ends_with(')')
+                && trimmed.matches(')').count() > trimmed.matches('(').count()
+            {
+                &trimmed[..trimmed.len() - 1]
+            } else {
+                trimmed
+            };
+            if trimmed.len() == url.len() {
+                break;
+            }
+            url = trimmed;
+        }
+
+        let host = url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .split(['/', '?', '#'])
+            .next()
+            .unwrap_or("");
+        if !host.contains('.') || host.ends_with('.') {
+            return false;
+        }
+
+        let href = if url.starts_with("www.") {
+            format!("http://{}", url)
+        } else {
+            url.to_string()
+        };
+        let html = self.renderer.link_html(&href, None, &escape_html(url));
+        let len = url.chars().count();
+        self.push_text(&html);
+        self.pos += len;
+        true
+    }
+
+    fn push_literal(&mut self, len: usize) {
+        let text: String = self.chars[self.pos..self.pos + len].iter().collect();
+        self.push_text(&text);
+        self.pos += len;
+    }
+
+    /// Finds the `]` closing a bracket whose content starts at `from`, skipping
+    /// nested brackets, escapes and code spans
+    fn find_closing_bracket(&self, from: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut i = from;
+        while i < self.chars.len() {
+            match self.chars[i] {
+                '\\' => i += 1,
+                '`' => {
+                    let run = self.run_length(i, '`');
+                    if let Some(end) = self.find_run(i + run, '`', run) {
+                        i = end + run - 1;
+                    } else {
+                        i += run - 1;
+                    }
+                }
+                '[' => depth += 1,
+                ']' if depth == 0 => return Some(i),
+                ']' => depth -= 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    }
+
+    fn parse_inline_html(&mut self) {
+        let rest = self.rest();
+
+        // Autolinks: <https://example.com> and <user@example.com>
+        let autolink = URI_AUTOLINK
+            .captures(rest)
+            .map(|cap| (cap[1].to_string(), cap[1].to_string()))
+            .or_else(|| {
+                EMAIL_AUTOLINK
+                    .captures(rest)
+                    .map(|cap| (format!("mailto:{}", &cap[1]), cap[1].to_string()))
+            });
+        if let Some((href, text)) = autolink {
+            let html = self.renderer.link_html(&href, None, &escape_html(&text));
+            self.push_text(&html);
+            self.pos += text.chars().count() + 2;
+            return;
+        }
+
+        // Raw tags such as <br> or <a href="..."> are passed through unchanged
+        if let Some(m) = INLINE_HTML_TAG.find(rest) {
+            let tag = m.as_str().to_string();
+            self.push_text(&tag);
+            self.pos += tag.chars().count();
+        } else {
+            self.push_text("<");
+            self.pos += 1;
+        }
+    }
+
+    fn parse_delimiter_run(&mut self, ch: char) {
+        let count = self.run_length(self.pos, ch);
+
+        // `~` and `=` only form delimiters in runs of exactly two
+        if matches!(ch, '~' | '=') && count != 2 {
+            self.push_literal(count);
+            return;
+        }
+
+        let before = if self.pos == 0 {
+            None
+        } else {
+            Some(self.chars[self.pos - 1])
+        };
+        let after = self.chars.get(self.pos + count).copied();
+
+        let left_flanking = is_left_flanking(before, after);
+        let right_flanking = is_left_flanking(after, before);
+
+        let (can_open, can_close) = if ch == '_' {
+            (
+                left_flanking && (!right_flanking || before.is_some_and(is_punctuation)),
+                right_flanking && (!left_flanking || after.is_some_and(is_punctuation)),
+            )
+        } else {
+            (left_flanking, right_flanking)
+        };
+
+        self.nodes.push(Node::Delim(Delimiter {
+            ch,
+            count,
+            original_count: count,
+            can_open,
+            can_close,
+            active: true,
+            open_tags: Vec::new(),
+            close_tags: Vec::new(),
+        }));
+        self.pos += count;
+    }
+
+    fn run_length(&self, from: usize, ch: char) -> usize {
+        self.chars[from..].iter().take_while(|&&c| c == ch).count()
+    }
+
+    /// Finds the next run of exactly `len` `ch` characters at or after `from`
+    fn find_run(&self, from: usize, ch: char, len: usize) -> Option<usize> {
+        let mut i = from;
+        while i < self.chars.len() {
+            if self.chars[i] == ch {
+                let run = self.run_length(i, ch);
+                if run == len {
+                    return Some(i);
+                }
+                i += run;
+            } else {
+                i += 1;
+            }
+        }
+        None
+    }
+
+    /// Matches delimiter runs into emphasis following the CommonMark
+    /// "process emphasis" procedure
+    fn process_emphasis(&mut self) {
+        let mut closer = 0;
+        while closer < self.nodes.len() {
+            let Some(closer_delim) = self.delimiter(closer) else {
+                closer += 1;
+                continue;
+            };
+            if !(closer_delim.active && closer_delim.can_close && closer_delim.count > 0) {
+                closer += 1;
+                continue;
+            }
+
+            let opener = (0..closer).rev().find(|&idx| {
+                self.delimiter(idx)
+                    .is_some_and(|opener| is_match(opener, closer_delim))
+            });
+
+            let Some(opener) = opener else {
+                if !closer_delim.can_open {
+                    self.delimiter_mut(closer).unwrap().active = false;
+                }
+                closer += 1;
+                continue;
+            };
+
+            let opener_count = self.delimiter(opener).unwrap().count;
+            let closer_count = closer_delim.count;
+            let ch = closer_delim.ch;
+            let used = if opener_count >= 2 && closer_count >= 2 {
+                2
+            } else {
+                1
+            };
+            let tag = match (ch, used) {
+                ('~', _) => "s",
+                ('=', _) => "mark",
+                (_, 2) => "b",
+                _ => "i",
+            };
+
+            let opener_delim = self.delimiter_mut(opener).unwrap();
+            opener_delim.count -= used;
+            opener_delim.open_tags.insert(0, tag);
+
+            let closer_delim = self.delimiter_mut(closer).unwrap();
+            closer_delim.count -= used;
+            closer_delim.close_tags.push(tag);
+            let closer_done = closer_delim.count == 0;
+
+            // Delimiters between the pair can no longer form emphasis
+            for idx in opener + 1..closer {
+                if let Some(delim) = self.delimiter_mut(idx) {
+                    delim.active = false;
+                }
+            }
+
+            if closer_done {
+                closer += 1;
+            }
+        }
+    }
+
+    fn delimiter(&self, idx: usize) -> Option<&Delimiter> {
+        match &self.nodes[idx] {
+            Node::Delim(delim) => Some(delim),
+            Node::Html(_) => None,
+        }
+    }
+
+    fn delimiter_mut(&mut self, idx: usize) -> Option<&mut Delimiter> {
+        match &mut self.nodes[idx] {
+            Node::Delim(delim) => Some(delim),
+            Node::Html(_) => None,
+        }
+    }
+}
+
+/// Whether `opener` can be closed by `closer`
+fn is_match(opener: &Delimiter, closer: &Delimiter) -> bool {
+    if opener.ch != closer.ch || !opener.active || !opener.can_open || opener.count == 0 {
+        return false;
+    }
+    if matches!(closer.ch, '~' | '=') {
+        return opener.count == 2 && closer.count == 2;
+    }
+
+    // Rule of three: a run that can both open and close only matches when the
+    // combined length is not a multiple of three, unless both are
+    let both_ways = opener.can_close || closer.can_open;
+    let sum = opener.original_count + closer.original_count;
+    !(both_ways
+        && sum.is_multiple_of(3)
+        && !(opener.original_count.is_multiple_of(3) && closer.original_count.is_multiple_of(3)))
+}
+
+/// Whether a delimiter run between `before` and `after` is left-flanking
+fn is_left_flanking(before: Option<char>, after: Option<char>) -> bool {
+    let Some(after) = after else {
+        return false;
+    };
+    if after.is_whitespace() {
+        return false;
+    }
+    !is_punctuation(after) || before.is_none_or(|b| b.is_whitespace() || is_punctuation(b))
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || (!c.is_alphanumeric() && !c.is_whitespace())
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_attribute(text: &str) -> String {
+    escape_html(text).replace('"', "&quot;")
+}
+
+/// Percent-encodes characters that are not valid in an `href` attribute.
+/// Existing `%XX` escapes and query separators such as `&` are kept as is.
+fn escape_url(url: &str) -> String {
+    let mut result = String::new();
+    for c in url.chars() {
+        if c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c) {
+            result.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                result.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_inline(text: &str) -> String {
+        InlineRenderer::new(&LinkReferences::new(), &ConversionOptions::default()).render(text)
+    }
+
+    #[test]
+    fn test_code_span_is_not_formatted() {
+        assert_eq!(
+            render_inline("Use `a_b * c_d` here"),
+            r#"Use <code class="inline-code">a_b * c_d</code> here"#
+        );
+        assert_eq!(
+            render_inline("`` a ` b ``"),
+            r#"<code class="inline-code">a ` b</code>"#
+        );
+        assert_eq!(
+            render_inline("`<div>`"),
+            r#"<code class="inline-code">&lt;div&gt;</code>"#
+        );
+    }
+
+    #[test]
+    fn test_emphasis_forms() {
+        assert_eq!(render_inline("*italic*"), "<i>italic</i>");
+        assert_eq!(render_inline("_italic_"), "<i>italic</i>");
+        assert_eq!(render_inline("**bold**"), "<b>bold</b>");
+        assert_eq!(render_inline("__bold__"), "<b>bold</b>");
+        assert_eq!(render_inline("***both***"), "<i><b>both</b></i>");
+        assert_eq!(render_inline("~~gone~~"), "<s>gone</s>");
+        assert_eq!(render_inline("==marked=="), "<mark>marked</mark>");
+    }
+
+    #[test]
+    fn test_nested_emphasis() {
+        assert_eq!(
+            render_inline("**bold _and italic_**"),
+            "<b>bold <i>and italic</i></b>"
+        );
+        assert_eq!(render_inline("*a **b** c*"), "<i>a <b>b</b> c</i>");
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(render_inline(r"\*not italic\*"), "*not italic*");
+        assert_eq!(render_inline(r"\`not code\`"), "`not code`");
+        assert_eq!(render_inline(r"a \< b"), "a &lt; b");
+    }
+
+    #[test]
+    fn test_intraword_underscores_are_literal() {
+        assert_eq!(render_inline("snake_case_name"), "snake_case_name");
+        assert_eq!(
+            render_inline("[docs](https://example.com/some_page_here)"),
+            r#"<a href="https://example.com/some_page_here" target="_blank">docs</a>"#
+        );
+    }
+
+    #[test]
+    fn test_link_title_and_url_escaping() {
+        assert_eq!(
+            render_inline(r#"[map](https://example.com 'Trail "map"')"#),
+            r#"<a href="https://example.com" title="Trail &quot;map&quot;" target="_blank">map</a>"#
+        );
+        assert_eq!(
+            render_inline(r#"[map](<https://example.com/a b> "Trail map")"#),
+            r#"<a href="https://example.com/a%20b" title="Trail map" target="_blank">map</a>"#
+        );
+        assert_eq!(
+            render_inline("[å](https://example.com/å?q=\"x\")"),
+            r#"<a href="https://example.com/%C3%A5?q=%22x%22" target="_blank">å</a>"#
+        );
+    }
+
+    #[test]
+    fn test_reference_links() {
+        let mut references = LinkReferences::new();
+        references.insert(
+            "osm".to_string(),
+            LinkReference {
+                url: "https://www.openstreetmap.org".to_string(),
+                title: Some("OSM".to_string()),
+            },
+        );
+        let renderer = InlineRenderer::new(&references, &ConversionOptions::default());
+        let expected = r#"<a href="https://www.openstreetmap.org" title="OSM" target="_blank">"#;
+
+        assert_eq!(
+            renderer.render("[the map][OSM]"),
+            format!("{}the map</a>", expected)
+        );
+        assert_eq!(renderer.render("[OSM][]"), format!("{}OSM</a>", expected));
+        assert_eq!(renderer.render("[osm]"), format!("{}osm</a>", expected));
+        assert_eq!(renderer.render("[unknown]"), "[unknown]");
+    }
+
+    #[test]
+    fn test_autolinks() {
+        assert_eq!(
+            render_inline("<https://example.com/a_b>"),
+            r#"<a href="https://example.com/a_b" target="_blank">https://example.com/a_b</a>"#
+        );
+        assert_eq!(
+            render_inline("Mail <info@example.com>"),
+            r#"Mail <a href="mailto:info@example.com" target="_blank">info@example.com</a>"#
+        );
+        assert_eq!(
+            render_inline("See https://example.com/path_(x). Or www.example.com!"),
+            r#"See <a href="https://example.com/path_(x)" target="_blank">https://example.com/path_(x)</a>. Or <a href="http://www.example.com" target="_blank">www.example.com</a>!"#
+        );
+        assert_eq!(render_inline("whatever http: is"), "whatever http: is");
+    }
+
+    #[test]
+    fn test_link_target_policy() {
+        let references = LinkReferences::new();
+        let options = ConversionOptions {
+            link_target: LinkTarget::External,
+            ..Default::default()
+        };
+        let renderer = InlineRenderer::new(&references, &options);
+        assert_eq!(
+            renderer.render("[a](https://example.com) [b](/local)"),
+            r#"<a href="https://example.com" target="_blank">a</a> <a href="/local">b</a>"#
+        );
+
+        let options = ConversionOptions {
+            link_target: LinkTarget::Never,
+            ..Default::default()
+        };
+        let renderer = InlineRenderer::new(&references, &options);
+        assert_eq!(
+            renderer.render("[a](https://example.com)"),
+            r#"<a href="https://example.com">a</a>"#
+        );
+    }
+
+    #[test]
+    fn test_unmatched_delimiters_are_literal() {
+        assert_eq!(render_inline("2 * 3 = 6"), "2 * 3 = 6");
+        assert_eq!(render_inline("**open"), "**open");
+        assert_eq!(render_inline("a == b"), "a == b");
+    }
+}
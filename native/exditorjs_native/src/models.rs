@@ -1,3 +1,5 @@
+use crate::options::{ConversionOptions, DefinitionListStyle};
+
 /// Generates a unique ID for blocks
 pub fn generate_block_id() -> String {
     use std::collections::hash_map::RandomState;
@@ -33,6 +35,16 @@ pub struct EditorJsBlockWithId {
     pub data: BlockData,
     #[serde(rename = "type")]
     pub block_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tunes: Option<BlockTunes>,
+    /// ID of the toggle block containing this block
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        alias = "parentId",
+        rename = "parentId"
+    )]
+    pub parent_id: Option<String>,
 }
 
 /// Block data wrapper
@@ -48,7 +60,14 @@ pub enum BlockData {
     Raw(RawData),
     Table(TableData),
     Delimiter(DelimiterData),
-    Embed(EmbedData),
+    Embed(Box<EmbedData>),
+    Media(MediaData),
+    LinkTool(LinkToolData),
+    Warning(WarningData),
+    Math(MathData),
+    Diagram(DiagramData),
+    Toggle(ToggleData),
+    DefinitionList(DefinitionListData),
 }
 
 /// Represents an Editor.js block (internal representation)
@@ -84,11 +103,52 @@ pub enum EditorJsBlock {
 
     #[serde(rename = "embed")]
     Embed { data: EmbedData },
+
+    /// A self-hosted video or audio file
+    #[serde(rename = "media")]
+    Media { data: MediaData },
+
+    /// A link preview for the Editor.js LinkTool
+    #[serde(rename = "linkTool")]
+    LinkTool { data: LinkToolData },
+
+    #[serde(rename = "warning")]
+    Warning { data: WarningData },
+
+    #[serde(rename = "math")]
+    Math { data: MathData },
+
+    #[serde(rename = "definitionList")]
+    DefinitionList { data: DefinitionListData },
+
+    /// A collapsible toggle with its nested child blocks
+    #[serde(rename = "toggle")]
+    Toggle {
+        data: ToggleData,
+        children: Vec<EditorJsBlock>,
+    },
+
+    /// Diagram source, emitted with a configurable block type
+    #[serde(untagged, serialize_with = "serialize_diagram", skip_deserializing)]
+    Diagram {
+        block_type: String,
+        data: DiagramData,
+    },
+
+    /// A block with Editor.js block tunes, serialized as the block itself with
+    /// an extra `tunes` field
+    #[serde(untagged, serialize_with = "serialize_tuned", skip_deserializing)]
+    Tuned {
+        block: Box<EditorJsBlock>,
+        tunes: BlockTunes,
+    },
 }
 
 impl EditorJsBlock {
-    /// Convert to block with ID
-    pub fn with_id(self) -> EditorJsBlockWithId {
+    /// Convert a block without children to a block with ID. Toggle and tuned
+    /// blocks go through [`EditorJsBlock::with_ids`], which keeps their
+    /// children and tunes.
+    fn leaf_with_id(self) -> EditorJsBlockWithId {
         let (block_type, data) = match self {
             EditorJsBlock::Paragraph { data } => {
                 ("paragraph".to_string(), BlockData::Paragraph(data))
@@ -104,17 +164,98 @@ impl EditorJsBlock {
                 "delimiter".to_string(),
                 BlockData::Delimiter(DelimiterData {}),
             ),
-            EditorJsBlock::Embed { data } => ("embed".to_string(), BlockData::Embed(data)),
+            EditorJsBlock::Embed { data } => {
+                ("embed".to_string(), BlockData::Embed(Box::new(data)))
+            }
+            EditorJsBlock::Media { data } => ("media".to_string(), BlockData::Media(data)),
+            EditorJsBlock::LinkTool { data } => ("linkTool".to_string(), BlockData::LinkTool(data)),
+            EditorJsBlock::Warning { data } => ("warning".to_string(), BlockData::Warning(data)),
+            EditorJsBlock::Math { data } => ("math".to_string(), BlockData::Math(data)),
+            EditorJsBlock::Diagram { block_type, data } => (block_type, BlockData::Diagram(data)),
+            EditorJsBlock::DefinitionList { data } => (
+                "definitionList".to_string(),
+                BlockData::DefinitionList(data),
+            ),
+            EditorJsBlock::Toggle { .. } | EditorJsBlock::Tuned { .. } => {
+                unreachable!("toggle and tuned blocks are converted by with_ids")
+            }
         };
 
         EditorJsBlockWithId {
             id: generate_block_id(),
             data,
             block_type,
+            tunes: None,
+            parent_id: None,
+        }
+    }
+
+    /// Convert to blocks with IDs, flattening toggle blocks into the toggle
+    /// followed by its children. Children reference the toggle through
+    /// `parentId`, and the toggle's `fk` holds its own ID.
+    pub fn with_ids(self) -> Vec<EditorJsBlockWithId> {
+        match self {
+            EditorJsBlock::Toggle { mut data, children } => {
+                let id = generate_block_id();
+                data.fk = Some(id.clone());
+                data.items = children.len();
+                let toggle = EditorJsBlockWithId {
+                    id: id.clone(),
+                    data: BlockData::Toggle(data),
+                    block_type: "toggle".to_string(),
+                    tunes: None,
+                    parent_id: None,
+                };
+
+                let mut blocks = vec![toggle];
+                for child in children {
+                    let mut child_blocks = child.with_ids();
+                    child_blocks[0].parent_id = Some(id.clone());
+                    blocks.extend(child_blocks);
+                }
+                blocks
+            }
+            EditorJsBlock::Tuned { block, tunes } => {
+                let mut blocks = block.with_ids();
+                blocks[0].tunes = Some(tunes);
+                blocks
+            }
+            block => vec![block.leaf_with_id()],
         }
     }
 }
 
+/// Serializes a tuned block as the block itself with an extra `tunes` field
+fn serialize_tuned<S: serde::Serializer>(
+    block: &EditorJsBlock,
+    tunes: &BlockTunes,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::Error;
+    use serde::Serialize;
+
+    let mut value = serde_json::to_value(block).map_err(S::Error::custom)?;
+    if let serde_json::Value::Object(map) = &mut value {
+        let tunes = serde_json::to_value(tunes).map_err(S::Error::custom)?;
+        map.insert("tunes".to_string(), tunes);
+    }
+    value.serialize(serializer)
+}
+
+/// Serializes a diagram block with its configured block type
+fn serialize_diagram<S: serde::Serializer>(
+    block_type: &str,
+    data: &DiagramData,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+
+    let mut state = serializer.serialize_struct("EditorJsBlock", 2)?;
+    state.serialize_field("type", block_type)?;
+    state.serialize_field("data", data)?;
+    state.end()
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct ParagraphData {
     pub text: String,
@@ -151,6 +292,106 @@ pub struct ListItem {
     pub items: Vec<ListItem>,
 }
 
+/// Builds paragraph blocks from paragraph text. Empty text yields no block, and
+/// with [`ConversionOptions::split_paragraphs_on_double_br`] set, text separated
+/// by `<br><br>` becomes separate paragraphs.
+pub(crate) fn paragraph_blocks(text: &str, options: &ConversionOptions) -> Vec<EditorJsBlock> {
+    let texts = if options.split_paragraphs_on_double_br {
+        let re = regex::Regex::new(r"(?:<br>\s*){2,}").unwrap();
+        re.split(text)
+            .map(|part| {
+                part.trim_start_matches("<br>")
+                    .trim_end_matches("<br>")
+                    .trim()
+            })
+            .map(|part| part.to_string())
+            .collect()
+    } else {
+        vec![text.to_string()]
+    };
+
+    texts
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .map(|text| EditorJsBlock::Paragraph {
+            data: ParagraphData { text },
+        })
+        .collect()
+}
+
+/// Builds a definition list in the configured
+/// [`ConversionOptions::definition_lists`] style
+pub(crate) fn definition_list_block(
+    items: Vec<DefinitionListItem>,
+    options: &ConversionOptions,
+) -> EditorJsBlock {
+    match options.definition_lists {
+        DefinitionListStyle::List => EditorJsBlock::List {
+            data: ListData {
+                style: "unordered".to_string(),
+                items: items
+                    .into_iter()
+                    .map(|item| ListItem {
+                        content: item.term,
+                        meta: ListItemMeta::default(),
+                        items: item
+                            .definitions
+                            .into_iter()
+                            .map(|content| ListItem {
+                                content,
+                                meta: ListItemMeta::default(),
+                                items: Vec::new(),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                meta: None,
+            },
+        },
+        DefinitionListStyle::Block => EditorJsBlock::DefinitionList {
+            data: DefinitionListData { items },
+        },
+    }
+}
+
+/// Returns the author of a quote attribution line such as `— Author`
+pub(crate) fn quote_attribution(line: &str) -> Option<&str> {
+    let line = line.trim();
+    ["\u{2014}", "\u{2015}", "\u{2013}", "--"]
+        .iter()
+        .find_map(|dash| line.strip_prefix(dash))
+        .map(|author| author.trim_start_matches('-').trim())
+        .filter(|author| !author.is_empty())
+}
+
+/// Inline markup for LaTeX math inside text
+pub(crate) fn inline_math_html(tex: &str) -> String {
+    let tex = tex
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(r#"<span class="math inline">{}</span>"#, tex)
+}
+
+/// Default warning title for an admonition kind such as `note` or `WARNING`
+pub(crate) fn warning_title(kind: &str) -> String {
+    let kind = kind.to_lowercase();
+    let mut chars = kind.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Sets `checked: false` on every item (recursively) that has no checkbox state,
+/// as Editor.js checklists expect the flag on all items
+pub(crate) fn mark_unchecked_items(items: &mut [ListItem]) {
+    for item in items {
+        item.meta.checked.get_or_insert(false);
+        mark_unchecked_items(&mut item.items);
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct ListData {
     pub style: String,
@@ -217,4 +458,151 @@ pub struct EmbedData {
     pub height: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub caption: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<EmbedMeta>,
+    /// Title of the embedded content, from oEmbed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_url: Option<String>,
+    /// Width divided by height, for sizing embeds responsively
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f64>,
+}
+
+/// Map view of an embedded map
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default, PartialEq)]
+pub struct EmbedMeta {
+    /// Map center as `[latitude, longitude]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center: Option<[f64; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<f64>,
+    /// Bounding box as `[min longitude, min latitude, max longitude, max latitude]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 4]>,
+}
+
+/// Link preview of the Editor.js LinkTool
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct LinkToolData {
+    pub link: String,
+    #[serde(default)]
+    pub meta: LinkMeta,
+}
+
+/// Metadata of a linked page, empty until filled by a
+/// [`LinkMetadataProvider`](crate::link_tool::LinkMetadataProvider)
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default, PartialEq)]
+pub struct LinkMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<LinkImage>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct LinkImage {
+    pub url: String,
+}
+
+/// Whether a media block plays video or audio
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+/// A video or audio file, played with the browser's own player
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct MediaData {
+    pub url: String,
+    pub kind: MediaKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Image shown before the video plays
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poster: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub controls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoplay: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "loop")]
+    pub looping: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muted: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct WarningData {
+    pub title: String,
+    pub message: String,
+}
+
+/// LaTeX source of a math block
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct MathData {
+    pub text: String,
+}
+
+/// Source of a diagram (Mermaid, PlantUML, Graphviz, ...)
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct DiagramData {
+    pub code: String,
+    pub language: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct DefinitionListData {
+    pub items: Vec<DefinitionListItem>,
+}
+
+/// A term of a definition list with its definitions. Several terms sharing
+/// the same definitions are joined with `<br>`.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct DefinitionListItem {
+    pub term: String,
+    pub definitions: Vec<String>,
+}
+
+/// Title and state of a toggle block, in the shape used by
+/// `editorjs-toggle-block`
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct ToggleData {
+    pub text: String,
+    /// `"open"` or `"closed"`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fk: Option<String>,
+    /// Number of child blocks following the toggle
+    #[serde(default)]
+    pub items: usize,
+}
+
+/// Block tunes attached to a block
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default)]
+pub struct BlockTunes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footnotes: Option<Vec<Footnote>>,
+}
+
+/// A footnote of the `footnotes` block tune
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct Footnote {
+    pub id: String,
+    pub content: String,
+    pub superscript: u32,
 }
@@ -0,0 +1,88 @@
+use crate::embed::EmbedRegistry;
+use std::collections::HashMap;
+
+/// Options controlling how HTML and Markdown are converted to Editor.js blocks
+#[derive(Debug, Clone, Default)]
+pub struct ConversionOptions {
+    /// How block content nested inside list items (code blocks, quotes, images,
+    /// tables) is converted, since Editor.js list items can only hold inline text
+    pub list_item_blocks: ListItemBlocks,
+    /// Split paragraphs on a double line break (`<br><br>`) into separate
+    /// paragraph blocks
+    pub split_paragraphs_on_double_br: bool,
+    /// Which links created from Markdown get `target="_blank"`
+    pub link_target: LinkTarget,
+    /// How footnotes (`[^1]` in Markdown, `<sup><a href="#fn1">` in HTML) are
+    /// emitted
+    pub footnotes: FootnoteStyle,
+    /// Code languages (such as `mermaid`, `plantuml` or `graphviz`) converted to
+    /// diagram blocks instead of code blocks, mapped to the block type to emit
+    /// (such as `diagram`, or the name of a custom Editor.js tool)
+    pub diagram_blocks: HashMap<String, String>,
+    /// How definition lists (`<dl>` in HTML, `Term` / `: Definition` in
+    /// Markdown) are emitted
+    pub definition_lists: DefinitionListStyle,
+    /// Embed services recognized in links and iframes; defaults to the
+    /// built-in services
+    pub embeds: EmbedRegistry,
+
+    /// How links standing alone in a paragraph or Markdown line, that are
+    /// neither embeds nor media files, are emitted
+    pub standalone_links: StandaloneLinkStyle,
+}
+
+/// Handling of block content found inside list items
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListItemBlocks {
+    /// Emit the nested blocks as separate blocks right after the list
+    #[default]
+    Hoist,
+    /// Keep the text of the nested blocks as extra lines of the item content;
+    /// content without text (such as images) is dropped
+    Inline,
+}
+
+/// Policy for adding `target="_blank"` to links created from Markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkTarget {
+    /// Open every link in a new tab
+    #[default]
+    Always,
+    /// Only open absolute `http(s)` links in a new tab
+    External,
+    /// Never add a `target` attribute
+    Never,
+}
+
+/// Output style for footnotes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FootnoteStyle {
+    /// Number the references and append the footnotes as an ordered list with
+    /// back-links after the last block
+    #[default]
+    List,
+    /// Attach the footnotes to the referencing block as a `footnotes` block tune
+    Tune,
+}
+
+/// Output style for definition lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefinitionListStyle {
+    /// An unordered list with each term as an item and its definitions as
+    /// child items
+    #[default]
+    List,
+    /// A dedicated `definitionList` block
+    Block,
+}
+
+/// Output style for standalone links
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StandaloneLinkStyle {
+    /// A paragraph holding the link
+    #[default]
+    Paragraph,
+    /// A `linkTool` block, with metadata left empty for a
+    /// [`LinkMetadataProvider`](crate::link_tool::LinkMetadataProvider)
+    LinkTool,
+}
@@ -41,4 +41,70 @@ defmodule ExditorJS.ConfigTest do
       assert document["version"] == "2.25.0"
     end
   end
+
+  describe "embeds configuration" do
+    setup do
+      original = Application.get_env(:exditorjs, :embeds)
+
+      on_exit(fn ->
+        if original do
+          Application.put_env(:exditorjs, :embeds, original)
+        else
+          Application.delete_env(:exditorjs, :embeds)
+        end
+      end)
+    end
+
+    test "adds custom embed services from the application env" do
+      Application.put_env(:exditorjs, :embeds,
+        services: [
+          [
+            name: "trailmap",
+            pattern: ~S"maps\.example\.com/t/(\w+)",
+            embed_url: "https://maps.example.com/embed/{}",
+            width: 800,
+            height: 600
+          ]
+        ]
+      )
+
+      {:ok, document} = ExditorJS.markdown_to_editorjs("https://maps.example.com/t/kungsleden")
+      [block] = document["blocks"]
+
+      assert block["type"] == "embed"
+      assert block["data"]["service"] == "trailmap"
+      assert block["data"]["embed"] == "https://maps.example.com/embed/kungsleden"
+      assert block["data"]["width"] == 800
+
+      {:ok, document} = ExditorJS.markdown_to_editorjs("https://vimeo.com/123456789")
+      assert hd(document["blocks"])["data"]["service"] == "vimeo"
+    end
+
+    test "loads embed services from a TOML file" do
+      path = Path.join(System.tmp_dir!(), "exditorjs_embeds_#{System.unique_integer()}.toml")
+
+      File.write!(path, """
+      [[services]]
+      name = "trailmap"
+      pattern = 'maps\\.example\\.com/t/(\\w+)'
+      embed_url = "https://maps.example.com/embed/{}"
+      """)
+
+      on_exit(fn -> File.rm(path) end)
+      Application.put_env(:exditorjs, :embeds, path)
+
+      html = ~s|<p><a href="https://maps.example.com/t/x1">Map</a></p>|
+      {:ok, document} = ExditorJS.html_to_editorjs(html)
+      assert hd(document["blocks"])["data"]["service"] == "trailmap"
+    end
+
+    test "returns an error for an invalid config" do
+      Application.put_env(:exditorjs, :embeds,
+        services: [[name: "broken", pattern: "(", embed_url: "x"]]
+      )
+
+      assert {:error, message} = ExditorJS.markdown_to_editorjs("Text")
+      assert message =~ "broken"
+    end
+  end
 end